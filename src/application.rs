@@ -22,16 +22,29 @@ use adw::subclass::prelude::*;
 use gettextrs::gettext;
 use gtk::prelude::*;
 use gtk::{gio, glib};
+use std::cell::{Cell, RefCell};
 
 use crate::config::{APPLICATION_ID, VERSION};
+use crate::dbus;
+use crate::link::Link;
+use crate::search_provider;
+use crate::timer::TimerService;
 use crate::views::search::SearchWindow;
-use crate::views::{BackupWindow, IPlanWindow};
+use crate::views::{
+    BackupWindow, CalendarWindow, IPlanWindow, PreferencesWindow, QuickAddWindow, StatisticsWindow,
+    TimeConflictsWindow,
+};
 
 mod imp {
     use super::*;
 
     #[derive(Debug, Default)]
-    pub struct IPlanApplication {}
+    pub struct IPlanApplication {
+        pub dbus_registration: RefCell<Option<gio::RegistrationId>>,
+        pub search_provider_registration: RefCell<Option<gio::RegistrationId>>,
+        // Launched with --quick-add, the main window isn't needed
+        pub quick_add_only: Cell<bool>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for IPlanApplication {
@@ -48,16 +61,88 @@ mod imp {
             obj.set_accels_for_action("app.quit", &["<primary>q"]);
             obj.set_accels_for_action("app.shortcuts", &["<primary>question"]);
             obj.set_accels_for_action("app.search", &["<primary>f"]);
+            obj.set_accels_for_action("app.preferences", &["<primary>comma"]);
+            obj.set_accels_for_action("app.quick-add", &["<primary>n"]);
+            obj.add_main_option(
+                "quick-add",
+                glib::Char::from(b'n'),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::None,
+                &gettext("Open the quick add window"),
+                None,
+            );
         }
     }
 
     impl ApplicationImpl for IPlanApplication {
+        fn startup(&self) {
+            self.parent_startup();
+            // Only the primary instance owns the bus name
+            let application = self.obj();
+            if let (Some(connection), Some(object_path)) = (
+                application.dbus_connection(),
+                application.dbus_object_path(),
+            ) {
+                match dbus::register(&connection, &object_path) {
+                    Ok(registration) => {
+                        self.dbus_registration.replace(Some(registration));
+                    }
+                    Err(err) => eprintln!("Failed to register D-Bus interface: {err}"),
+                }
+                match search_provider::register(&connection, &object_path) {
+                    Ok(registration) => {
+                        self.search_provider_registration
+                            .replace(Some(registration));
+                    }
+                    Err(err) => eprintln!("Failed to register search provider: {err}"),
+                }
+                TimerService::default().connect_closure(
+                    "changed",
+                    false,
+                    glib::closure_local!(|_: TimerService| dbus::emit_timer_changed()),
+                );
+            }
+        }
+
+        // Quick add is opened in the running instance when there is one
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if options.contains("quick-add") {
+                let application = self.obj();
+                if let Err(err) = application.register(gio::Cancellable::NONE) {
+                    eprintln!("Failed to register application: {err}");
+                    return glib::ExitCode::FAILURE;
+                }
+                if application.is_remote() {
+                    application.activate_action("quick-add", None);
+                    return glib::ExitCode::SUCCESS;
+                }
+                self.quick_add_only.set(true);
+            }
+            self.parent_handle_local_options(options)
+        }
+
+        fn shutdown(&self) {
+            if let Some(connection) = self.obj().dbus_connection() {
+                if let Some(registration) = self.dbus_registration.take() {
+                    let _ = connection.unregister_object(registration);
+                }
+                if let Some(registration) = self.search_provider_registration.take() {
+                    let _ = connection.unregister_object(registration);
+                }
+            }
+            self.parent_shutdown();
+        }
+
         // We connect to the activate callback to create a window when the application
         // has been launched. Additionally, this callback notifies us when the user
         // tries to launch a "second instance" of the application. When they try
         // to do that, we'll just present any existing window.
         fn activate(&self) {
             let application = self.obj();
+            if self.quick_add_only.take() {
+                application.show_quick_add();
+                return;
+            }
             // Get the current window or create one if necessary
             let window = if let Some(window) = application.active_window() {
                 window
@@ -72,6 +157,20 @@ mod imp {
             // Ask the window manager/compositor to present the window
             window.present();
         }
+
+        // Opens iplan:// links, from the command line or other applications
+        fn open(&self, files: &[gio::File], _hint: &str) {
+            self.activate();
+            let window = self.obj().active_window().and_downcast::<IPlanWindow>();
+            if let Some(window) = window {
+                for file in files {
+                    match Link::parse(&file.uri()) {
+                        Some(link) => link.open(&window),
+                        None => eprintln!("Unsupported link: {}", file.uri()),
+                    }
+                }
+            }
+        }
     }
 
     impl GtkApplicationImpl for IPlanApplication {}
@@ -103,25 +202,57 @@ impl IPlanApplication {
             .activate(move |app: &Self, _, _| app.show_shortcuts())
             .build();
         let search_action = gio::ActionEntry::builder("search")
-            .activate(move |app: &Self, _, _| app.show_search())
+            .activate(move |app: &Self, _, _| app.show_search(""))
             .build();
         let backup_action = gio::ActionEntry::builder("backup")
             .activate(move |app: &Self, _, _| app.show_backup())
             .build();
+        let preferences_action = gio::ActionEntry::builder("preferences")
+            .activate(move |app: &Self, _, _| app.show_preferences())
+            .build();
+        let time_conflicts_action = gio::ActionEntry::builder("time-conflicts")
+            .activate(move |app: &Self, _, _| app.show_time_conflicts())
+            .build();
+        let calendar_action = gio::ActionEntry::builder("calendar")
+            .activate(move |app: &Self, _, _| app.show_calendar())
+            .build();
+        let statistics_action = gio::ActionEntry::builder("statistics")
+            .activate(move |app: &Self, _, _| app.show_statistics())
+            .build();
+        let quick_add_action = gio::ActionEntry::builder("quick-add")
+            .activate(move |app: &Self, _, _| app.show_quick_add())
+            .build();
         self.add_action_entries([
             quit_action,
             about_action,
             shortcuts_action,
             search_action,
             backup_action,
+            preferences_action,
+            time_conflicts_action,
+            calendar_action,
+            statistics_action,
+            quick_add_action,
         ]);
     }
 
-    fn show_search(&self) {
+    // Shows tasks that are added from outside of the main windows
+    pub fn reload_windows(&self) {
+        for window in self.windows() {
+            if let Some(window) = window.downcast_ref::<IPlanWindow>() {
+                window.reload();
+            }
+        }
+    }
+
+    pub fn show_search(&self, text: &str) {
         let window = SearchWindow::new(
             self.upcast_ref::<gtk::Application>(),
             &self.active_window().unwrap(),
         );
+        if !text.is_empty() {
+            window.imp().search_entry.set_text(text);
+        }
         window.present();
     }
 
@@ -142,6 +273,35 @@ impl IPlanApplication {
         backup_window.present();
     }
 
+    fn show_preferences(&self) {
+        let active_window = self.active_window().unwrap();
+        let preferences_window = PreferencesWindow::new(self, &active_window);
+        preferences_window.present();
+    }
+
+    fn show_time_conflicts(&self) {
+        let active_window = self.active_window().unwrap();
+        let time_conflicts_window = TimeConflictsWindow::new(self, &active_window);
+        time_conflicts_window.present();
+    }
+
+    fn show_calendar(&self) {
+        let active_window = self.active_window().unwrap();
+        let calendar_window = CalendarWindow::new(self, &active_window);
+        calendar_window.present();
+    }
+
+    fn show_statistics(&self) {
+        let active_window = self.active_window().unwrap();
+        let statistics_window = StatisticsWindow::new(self, &active_window);
+        statistics_window.present();
+    }
+
+    fn show_quick_add(&self) {
+        let quick_add_window = QuickAddWindow::new(self);
+        quick_add_window.present();
+    }
+
     fn show_about(&self) {
         let window = self.active_window().unwrap();
         let about = adw::AboutWindow::builder()
@@ -0,0 +1,193 @@
+use gtk::{gio, glib};
+use soup::prelude::*;
+
+use crate::caldav::SyncError;
+use crate::config::VERSION;
+use crate::db::models::CalDavBinding;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag />
+    <c:calendar-data />
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VTODO" />
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+pub struct Response {
+    pub status: u32,
+    pub etag: Option<String>,
+    pub body: String,
+}
+
+pub struct RemoteTask {
+    pub href: String,
+    pub etag: String,
+    pub data: String,
+}
+
+// Redirects, proxies and Basic or Digest authentication are handled by libsoup
+pub struct Client {
+    session: soup::Session,
+    collection: glib::Uri,
+    username: String,
+    password: String,
+}
+
+impl Client {
+    pub fn new(binding: &CalDavBinding) -> Result<Self, SyncError> {
+        // Tasks are stored as members of the collection
+        let mut url = binding.url();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let collection =
+            glib::Uri::parse(&url, glib::UriFlags::NONE).map_err(|_| SyncError::InvalidUrl)?;
+        let has_host = collection.host().is_some_and(|host| !host.is_empty());
+        if !matches!(collection.scheme().as_str(), "http" | "https") || !has_host {
+            return Err(SyncError::InvalidUrl);
+        }
+        let username = binding.username();
+        let password = if username.is_empty() {
+            String::new()
+        } else {
+            binding.password().map_err(SyncError::Keyring)?
+        };
+        let session = soup::Session::new();
+        session.set_user_agent(&format!("IPlan/{VERSION}"));
+        session.set_timeout(30);
+        Ok(Self {
+            session,
+            collection,
+            username,
+            password,
+        })
+    }
+
+    pub fn task_href(&self, uid: &str) -> String {
+        format!("{}{uid}.ics", self.collection.path())
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        href: &str,
+        headers: &[(&str, &str)],
+        body: Option<(&str, &str)>,
+    ) -> Result<Response, SyncError> {
+        // Hrefs from the server are usually absolute paths
+        let uri = self
+            .collection
+            .parse_relative(href, glib::UriFlags::NONE)
+            .map_err(|_| SyncError::InvalidResponse)?;
+        let message = soup::Message::from_uri(method, &uri);
+        if let Some(request_headers) = message.request_headers() {
+            for (name, value) in headers {
+                request_headers.append(name, value);
+            }
+        }
+        if let Some((content_type, body)) = body {
+            let bytes = glib::Bytes::from(body.as_bytes());
+            message.set_request_body_from_bytes(Some(content_type), Some(&bytes));
+        }
+        if !self.username.is_empty() {
+            let (username, password) = (self.username.clone(), self.password.clone());
+            message.connect_authenticate(move |_message, auth, retrying| {
+                // Wrong credentials end with a 401 status
+                if retrying {
+                    return false;
+                }
+                auth.authenticate(&username, &password);
+                true
+            });
+        }
+        let body = self
+            .session
+            .send_and_read(&message, gio::Cancellable::NONE)?;
+        Ok(Response {
+            status: message.status_code(),
+            etag: message
+                .response_headers()
+                .and_then(|headers| headers.one("ETag"))
+                .map(String::from),
+            body: String::from_utf8_lossy(&body).to_string(),
+        })
+    }
+
+    pub fn list_tasks(&self) -> Result<Vec<RemoteTask>, SyncError> {
+        let response = self.request(
+            "REPORT",
+            &self.collection.path(),
+            &[("Depth", "1")],
+            Some(("application/xml; charset=utf-8", CALENDAR_QUERY)),
+        )?;
+        if response.status != 207 {
+            return Err(SyncError::Status(response.status));
+        }
+        parse_multistatus(&response.body)
+    }
+
+    // Returns the new etag, or None when the task is changed on the server meanwhile
+    pub fn put_task(
+        &self,
+        href: &str,
+        etag: Option<&str>,
+        data: &str,
+    ) -> Result<Option<String>, SyncError> {
+        let condition = match etag {
+            Some(etag) => ("If-Match", etag),
+            None => ("If-None-Match", "*"),
+        };
+        let response = self.request(
+            "PUT",
+            href,
+            &[condition],
+            Some(("text/calendar; charset=utf-8", data)),
+        )?;
+        match response.status {
+            200..=299 => Ok(Some(response.etag.unwrap_or_default())),
+            412 => Ok(None),
+            status => Err(SyncError::Status(status)),
+        }
+    }
+
+    pub fn delete_task(&self, href: &str, etag: &str) -> Result<(), SyncError> {
+        let response = self.request("DELETE", href, &[("If-Match", etag)], None)?;
+        match response.status {
+            200..=299 | 404 | 412 => Ok(()),
+            status => Err(SyncError::Status(status)),
+        }
+    }
+}
+
+// Responses of a multistatus without calendar data are skipped
+fn parse_multistatus(xml: &str) -> Result<Vec<RemoteTask>, SyncError> {
+    let document = roxmltree::Document::parse(xml).map_err(|_| SyncError::InvalidResponse)?;
+    let mut tasks = vec![];
+    for response in document
+        .descendants()
+        .filter(|node| node.has_tag_name((DAV, "response")))
+    {
+        let text = |name: (&str, &str)| {
+            response
+                .descendants()
+                .find(|node| node.has_tag_name(name))
+                .and_then(|node| node.text())
+                .map(|text| text.trim().to_string())
+        };
+        let href = text((DAV, "href"));
+        let etag = text((DAV, "getetag"));
+        let data = text((CALDAV, "calendar-data"));
+        if let (Some(href), Some(etag), Some(data)) = (href, etag, data) {
+            tasks.push(RemoteTask { href, etag, data });
+        }
+    }
+    Ok(tasks)
+}
@@ -0,0 +1,53 @@
+mod client;
+mod secret;
+mod sync;
+pub use secret::{clear_password, lookup_password, store_password};
+pub use sync::sync_project;
+
+use gettextrs::gettext;
+use gtk::glib;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum SyncError {
+    Connection(glib::Error),
+    Status(u32),
+    Database(rusqlite::Error),
+    Keyring(glib::Error),
+    InvalidUrl,
+    InvalidResponse,
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Connection(error) => {
+                write!(f, "{}: {}", gettext("Connection failed"), error.message())
+            }
+            SyncError::Status(401) | SyncError::Status(403) => {
+                write!(f, "{}", gettext("Authentication failed"))
+            }
+            SyncError::Status(status) => {
+                write!(f, "{} {status}", gettext("Server responded with status"))
+            }
+            SyncError::Database(error) => write!(f, "{}: {error}", gettext("Database error")),
+            SyncError::Keyring(error) => {
+                write!(f, "{}: {}", gettext("Keyring error"), error.message())
+            }
+            SyncError::InvalidUrl => write!(f, "{}", gettext("Invalid calendar address")),
+            SyncError::InvalidResponse => write!(f, "{}", gettext("Invalid server response")),
+        }
+    }
+}
+
+impl From<glib::Error> for SyncError {
+    fn from(error: glib::Error) -> Self {
+        SyncError::Connection(error)
+    }
+}
+
+impl From<rusqlite::Error> for SyncError {
+    fn from(error: rusqlite::Error) -> Self {
+        SyncError::Database(error)
+    }
+}
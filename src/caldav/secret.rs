@@ -0,0 +1,43 @@
+use gtk::{gio, glib};
+use libsecret::{Schema, SchemaAttributeType, SchemaFlags};
+use std::collections::HashMap;
+
+// Passwords of CalDAV bindings live in the keyring, bindings only keep the key
+fn schema() -> Schema {
+    let attributes = HashMap::from([("key", SchemaAttributeType::String)]);
+    Schema::new("ir.imansalmani.IPlan.CalDav", SchemaFlags::NONE, attributes)
+}
+
+pub fn store_password(key: &str, url: &str, password: &str) -> Result<(), glib::Error> {
+    libsecret::password_store_sync(
+        Some(&schema()),
+        HashMap::from([("key", key)]),
+        Some(libsecret::COLLECTION_DEFAULT.as_str()),
+        &format!("IPlan CalDAV: {url}"),
+        password,
+        gio::Cancellable::NONE,
+    )
+}
+
+pub fn lookup_password(key: &str) -> Result<String, glib::Error> {
+    if key.is_empty() {
+        return Ok(String::new());
+    }
+    let password = libsecret::password_lookup_sync(
+        Some(&schema()),
+        HashMap::from([("key", key)]),
+        gio::Cancellable::NONE,
+    )?;
+    Ok(password.map(String::from).unwrap_or_default())
+}
+
+pub fn clear_password(key: &str) -> Result<(), glib::Error> {
+    if key.is_empty() {
+        return Ok(());
+    }
+    libsecret::password_clear_sync(
+        Some(&schema()),
+        HashMap::from([("key", key)]),
+        gio::Cancellable::NONE,
+    )
+}
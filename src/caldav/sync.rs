@@ -0,0 +1,255 @@
+use gettextrs::gettext;
+use gtk::glib;
+use std::collections::{HashMap, HashSet};
+
+use crate::caldav::client::{Client, RemoteTask};
+use crate::caldav::SyncError;
+use crate::db::models::{CalDavItem, Task};
+use crate::db::operations::{
+    create_caldav_item, create_list, create_task, delete_caldav_item, delete_task,
+    read_caldav_binding, read_caldav_items, read_lists, read_tasks, update_caldav_binding,
+    update_task,
+};
+use crate::exchange::icalendar::{
+    format_datetime, format_vtodo, parse_vtodos, task_uid, wrap_calendar, VTodo,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    // Used when a task is changed both here and on the server
+    #[default]
+    Server,
+    Local,
+}
+
+impl From<u8> for ConflictPolicy {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => ConflictPolicy::Local,
+            _ => ConflictPolicy::Server,
+        }
+    }
+}
+
+struct Sync {
+    client: Client,
+    project_id: i64,
+    policy: ConflictPolicy,
+    dtstamp: String,
+    list_id: Option<i64>,
+    // Task id to UID, needed for RELATED-TO of subtasks
+    uids: HashMap<i64, String>,
+}
+
+impl Sync {
+    fn parent_uid(&self, task: &Task) -> Option<String> {
+        (task.parent() != 0).then(|| {
+            self.uids
+                .get(&task.parent())
+                .cloned()
+                .unwrap_or_else(|| task_uid(task.parent()))
+        })
+    }
+
+    fn state(&self, task: &Task, uid: &str) -> String {
+        format_vtodo(task, uid, self.parent_uid(task).as_deref(), None)
+    }
+
+    fn task_id(&self, uid: &str) -> Option<i64> {
+        self.uids
+            .iter()
+            .find(|(_, task_uid)| *task_uid == uid)
+            .map(|(task_id, _)| *task_id)
+    }
+
+    // New tasks from the server go to the first list of the project
+    fn list_id(&mut self) -> Result<i64, SyncError> {
+        if let Some(list_id) = self.list_id {
+            return Ok(list_id);
+        }
+        let list_id = match read_lists(self.project_id)?.first() {
+            Some(list) => list.id(),
+            None => create_list(&gettext("Tasks"), self.project_id)?.id(),
+        };
+        self.list_id = Some(list_id);
+        Ok(list_id)
+    }
+
+    fn save_item(&self, task: &Task, uid: &str, href: &str, etag: String) -> Result<(), SyncError> {
+        create_caldav_item(&CalDavItem::new(
+            task.id(),
+            self.project_id,
+            uid.to_string(),
+            href.to_string(),
+            etag,
+            self.state(task, uid),
+        ))?;
+        Ok(())
+    }
+
+    fn upload(
+        &self,
+        task: &Task,
+        uid: &str,
+        href: &str,
+        etag: Option<&str>,
+    ) -> Result<(), SyncError> {
+        let vtodo = format_vtodo(
+            task,
+            uid,
+            self.parent_uid(task).as_deref(),
+            Some(&self.dtstamp),
+        );
+        // The task is left for the next sync if it changed on the server meanwhile
+        if let Some(etag) = self.client.put_task(href, etag, &wrap_calendar(&vtodo))? {
+            self.save_item(task, uid, href, etag)?;
+        }
+        Ok(())
+    }
+
+    fn apply(&self, task: &Task, vtodo: &VTodo) -> Result<(), SyncError> {
+        task.set_name(vtodo.summary.clone());
+        task.set_description(vtodo.description.clone());
+        task.set_done(vtodo.completed);
+        task.set_priority(vtodo.priority);
+        task.set_due_date(vtodo.due_date);
+        task.set_tags(vtodo.tags.join(" "));
+        update_task(task)?;
+        Ok(())
+    }
+
+    fn create(
+        &mut self,
+        remote_task: RemoteTask,
+        vtodo: VTodo,
+        tasks: &mut HashMap<i64, Task>,
+    ) -> Result<(), SyncError> {
+        // Uploaded in a previous sync that failed before saving the item
+        let uploaded = tasks
+            .values()
+            .find(|task| task_uid(task.id()) == vtodo.uid)
+            .map(Task::id);
+        let task = if let Some(task_id) = uploaded {
+            tasks.remove(&task_id).unwrap()
+        } else {
+            let parent = vtodo
+                .parent_uid
+                .as_deref()
+                .and_then(|parent_uid| self.task_id(parent_uid));
+            match parent {
+                Some(parent_id) => create_task(&vtodo.summary, self.project_id, 0, parent_id)?,
+                None => create_task(&vtodo.summary, self.project_id, self.list_id()?, 0)?,
+            }
+        };
+        self.apply(&task, &vtodo)?;
+        self.uids.insert(task.id(), vtodo.uid.clone());
+        self.save_item(&task, &vtodo.uid, &remote_task.href, remote_task.etag)
+    }
+}
+
+// Returns true if tasks of the project are changed
+pub fn sync_project(project_id: i64) -> Result<bool, SyncError> {
+    let binding = match read_caldav_binding(project_id)? {
+        Some(binding) => binding,
+        None => return Ok(false),
+    };
+    let client = Client::new(&binding)?;
+    let mut remote = HashMap::new();
+    for remote_task in client.list_tasks()? {
+        let vtodo = parse_vtodos(&remote_task.data)
+            .into_iter()
+            .find(|vtodo| !vtodo.uid.is_empty());
+        if let Some(vtodo) = vtodo {
+            remote.insert(vtodo.uid.clone(), (remote_task, vtodo));
+        }
+    }
+    let mut tasks: HashMap<i64, Task> = read_tasks(project_id, None, None, None)?
+        .into_iter()
+        .map(|task| (task.id(), task))
+        .collect();
+    let items = read_caldav_items(project_id)?;
+    let mut sync = Sync {
+        client,
+        project_id,
+        policy: ConflictPolicy::from(binding.policy()),
+        dtstamp: format_datetime(glib::DateTime::now_utc().unwrap().to_unix()),
+        list_id: None,
+        uids: items.iter().map(|item| (item.task(), item.uid())).collect(),
+    };
+    let mut changed = false;
+
+    // Tasks from previous syncs
+    for item in items {
+        let uid = item.uid();
+        match (tasks.remove(&item.task()), remote.remove(&uid)) {
+            (None, None) => delete_caldav_item(item.task())?,
+            (None, Some((remote_task, vtodo))) => {
+                delete_caldav_item(item.task())?;
+                if remote_task.etag != item.etag() && sync.policy == ConflictPolicy::Server {
+                    // Changed on the server after deleted here, so it will be created again
+                    remote.insert(uid, (remote_task, vtodo));
+                } else {
+                    sync.client
+                        .delete_task(&remote_task.href, &remote_task.etag)?;
+                }
+            }
+            (Some(task), None) => {
+                if sync.state(&task, &uid) != item.state() && sync.policy == ConflictPolicy::Local {
+                    sync.upload(&task, &uid, &item.href(), None)?;
+                } else {
+                    delete_task(task.id(), task.list(), task.position())?;
+                    delete_caldav_item(task.id())?;
+                    // Subtasks are deleted with their parent
+                    tasks.retain(|_, subtask| subtask.parent() != task.id());
+                    changed = true;
+                }
+            }
+            (Some(task), Some((remote_task, vtodo))) => {
+                let local_changed = sync.state(&task, &uid) != item.state();
+                let remote_changed = remote_task.etag != item.etag();
+                if remote_changed && (!local_changed || sync.policy == ConflictPolicy::Server) {
+                    sync.apply(&task, &vtodo)?;
+                    sync.save_item(&task, &uid, &remote_task.href, remote_task.etag)?;
+                    changed = true;
+                } else if local_changed {
+                    sync.upload(&task, &uid, &remote_task.href, Some(&remote_task.etag))?;
+                }
+            }
+        }
+    }
+
+    // Tasks created on the server, parents are created before their subtasks
+    let mut pending: Vec<(RemoteTask, VTodo)> = remote.into_values().collect();
+    while !pending.is_empty() {
+        let pending_uids: HashSet<String> =
+            pending.iter().map(|(_, vtodo)| vtodo.uid.clone()).collect();
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|(_, vtodo)| {
+            !vtodo
+                .parent_uid
+                .as_ref()
+                .is_some_and(|parent_uid| pending_uids.contains(parent_uid))
+        });
+        // Tasks left in a RELATED-TO cycle are created anyway
+        let (ready, waiting) = if ready.is_empty() {
+            (waiting, vec![])
+        } else {
+            (ready, waiting)
+        };
+        for (remote_task, vtodo) in ready {
+            sync.create(remote_task, vtodo, &mut tasks)?;
+            changed = true;
+        }
+        pending = waiting;
+    }
+
+    // Tasks created here
+    for task in tasks.values() {
+        let uid = task_uid(task.id());
+        let href = sync.client.task_href(&uid);
+        sync.upload(task, &uid, &href, None)?;
+    }
+
+    binding.set_last_sync(glib::DateTime::now_utc().unwrap().to_unix());
+    update_caldav_binding(&binding)?;
+    Ok(changed)
+}
@@ -0,0 +1,455 @@
+use gettextrs::gettext;
+use gtk::{glib, prelude::*};
+use rusqlite::Result;
+use std::collections::HashMap;
+
+use crate::db::models::{List, Project, Record, Task};
+use crate::db::operations::{
+    create_task, read_incomplete_records, read_lists, read_projects, read_records, read_task,
+    read_tasks, update_task,
+};
+use crate::dbus;
+use crate::timer;
+
+const USAGE: &str = "Usage:
+  iplan add NAME [--project PROJECT] [--list LIST] [--parent ID]
+  iplan list [--project PROJECT] [--list LIST] [--done] [--json]
+  iplan done ID
+  iplan timer start ID
+  iplan timer stop
+  iplan timer status [--json]
+  iplan report [--day | --week | --month] [--project PROJECT] [--json]
+  iplan --quick-add";
+
+const COMMANDS: [&str; 6] = ["add", "list", "done", "timer", "report", "help"];
+
+// Options that take a value, everything else starting with -- is a flag
+const VALUE_OPTIONS: [&str; 3] = ["project", "list", "parent"];
+
+// Options accepted by each command, others are a usage error
+fn command_options(command: &str) -> &'static [&'static str] {
+    match command {
+        "add" => &["project", "list", "parent"],
+        "list" => &["project", "list", "done", "json"],
+        "timer" => &["json"],
+        "report" => &["day", "week", "month", "project", "json"],
+        _ => &[],
+    }
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    fn parse(args: &[String], allowed: &[&str]) -> std::result::Result<Self, String> {
+        let mut positional = vec![];
+        let mut options = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(option) = arg.strip_prefix("--") {
+                let (name, value) = match option.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None if VALUE_OPTIONS.contains(&option) => (option, args.next().cloned()),
+                    None => (option, None),
+                };
+                if !allowed.contains(&name) {
+                    return Err(gettext("Unknown option --{}").replace("{}", name));
+                }
+                if VALUE_OPTIONS.contains(&name) && value.is_none() {
+                    return Err(gettext("Option --{} needs a value").replace("{}", name));
+                }
+                options.insert(name.to_string(), value);
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|value| value.as_deref())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+    Database(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for CliError {
+    fn from(error: rusqlite::Error) -> Self {
+        CliError::Database(error)
+    }
+}
+
+type CliResult = std::result::Result<(), CliError>;
+
+// Returns None when the arguments are for the graphical application
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?.as_str();
+    if !COMMANDS.contains(&command) {
+        return None;
+    }
+    let result = Args::parse(&args[1..], command_options(command))
+        .map_err(CliError::Usage)
+        .and_then(|args| match command {
+            "add" => add(&args),
+            "list" => list(&args),
+            "done" => done(&args),
+            "timer" => timer(&args),
+            "report" => report(&args),
+            _ => {
+                println!("{USAGE}");
+                Ok(())
+            }
+        });
+    match result {
+        Ok(()) => Some(0),
+        Err(CliError::Usage(message)) => {
+            eprintln!("{message}\n\n{USAGE}");
+            Some(2)
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("iplan: {message}");
+            Some(1)
+        }
+        Err(CliError::Database(error)) => {
+            eprintln!("iplan: {error}");
+            Some(1)
+        }
+    }
+}
+
+fn now() -> i64 {
+    glib::DateTime::now_local().unwrap().to_unix()
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn find_project(name: &str) -> std::result::Result<Project, CliError> {
+    read_projects(true)?
+        .into_iter()
+        .find(|project| project.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| CliError::Failed(gettext("Project \"{}\" not found").replace("{}", name)))
+}
+
+fn find_list(project: &Project, name: &str) -> std::result::Result<List, CliError> {
+    read_lists(project.id())?
+        .into_iter()
+        .find(|list| list.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| CliError::Failed(gettext("List \"{}\" not found").replace("{}", name)))
+}
+
+fn parse_id(value: Option<&str>) -> std::result::Result<i64, CliError> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| CliError::Usage(gettext("A task ID is required")))
+}
+
+fn read_existing_task(task_id: i64) -> std::result::Result<Task, CliError> {
+    match read_task(task_id) {
+        Ok(task) => Ok(task),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(CliError::Failed(
+            gettext("Task {} not found").replace("{}", &task_id.to_string()),
+        )),
+        Err(error) => Err(CliError::Database(error)),
+    }
+}
+
+fn add(args: &Args) -> CliResult {
+    let name = args.positional.join(" ");
+    if name.trim().is_empty() {
+        return Err(CliError::Usage(gettext("A task name is required")));
+    }
+    let task = if let Some(parent) = args.value("parent") {
+        let parent = read_existing_task(parse_id(Some(parent))?)?;
+        create_task(name.trim(), parent.project(), 0, parent.id())?
+    } else {
+        let project = match args.value("project") {
+            Some(name) => find_project(name)?,
+            None => Project::inbox_project()?,
+        };
+        let list = match args.value("list") {
+            Some(name) => find_list(&project, name)?,
+            None => read_lists(project.id())?
+                .into_iter()
+                .next()
+                .ok_or_else(|| CliError::Failed(gettext("The project has no list")))?,
+        };
+        create_task(name.trim(), project.id(), list.id(), 0)?
+    };
+    println!("{}", task.id());
+    Ok(())
+}
+
+fn task_json(task: &Task, project: &Project, list_name: &str) -> String {
+    format!(
+        "{{\"id\": {}, \"name\": {}, \"done\": {}, \"priority\": {}, \"project\": {}, \
+        \"list\": {}, \"parent\": {}, \"duration\": {}}}",
+        task.id(),
+        json_string(&task.name()),
+        task.done(),
+        task.priority(),
+        json_string(&project.name()),
+        json_string(list_name),
+        task.parent(),
+        task.duration()
+    )
+}
+
+fn list(args: &Args) -> CliResult {
+    let projects = match args.value("project") {
+        Some(name) => vec![find_project(name)?],
+        None => read_projects(false)?,
+    };
+    let done = if args.flag("done") { None } else { Some(false) };
+    let mut lines = vec![];
+    for project in projects {
+        let lists = match (args.value("project"), args.value("list")) {
+            (Some(_), Some(name)) => vec![find_list(&project, name)?],
+            // Projects without a list with this name are skipped
+            (None, Some(name)) => read_lists(project.id())?
+                .into_iter()
+                .filter(|list| list.name().eq_ignore_ascii_case(name))
+                .collect(),
+            (_, None) => read_lists(project.id())?,
+        };
+        for list in lists {
+            for task in read_tasks(project.id(), Some(list.id()), done, Some(0))? {
+                push_task_lines(&mut lines, args, &task, &project, &list.name(), 0)?;
+            }
+        }
+    }
+    if args.flag("json") {
+        println!("[{}]", lines.join(",\n "));
+    } else {
+        for line in lines {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+fn push_task_lines(
+    lines: &mut Vec<String>,
+    args: &Args,
+    task: &Task,
+    project: &Project,
+    list_name: &str,
+    depth: usize,
+) -> Result<()> {
+    // Tasks waiting for deletion are hidden in the interface too
+    if task.suspended() {
+        return Ok(());
+    }
+    if args.flag("json") {
+        lines.push(task_json(task, project, list_name));
+    } else {
+        let checkbox = if task.done() { "[x]" } else { "[ ]" };
+        lines.push(format!(
+            "{}\t{}{checkbox} {}\t{}/{}",
+            task.id(),
+            "  ".repeat(depth),
+            task.name(),
+            project.name(),
+            list_name
+        ));
+    }
+    let done = if args.flag("done") { None } else { Some(false) };
+    for subtask in read_tasks(project.id(), None, done, Some(task.id()))? {
+        push_task_lines(lines, args, &subtask, project, list_name, depth + 1)?;
+    }
+    Ok(())
+}
+
+// A running instance owns the timer, so timer changes go through it when it runs
+fn call_instance(method: &str, parameters: Option<&glib::Variant>) -> Option<CliResult> {
+    let result = dbus::call_instance(method, parameters)?;
+    Some(
+        result
+            .map(|_| ())
+            .map_err(|err| CliError::Failed(err.message().to_string())),
+    )
+}
+
+fn done(args: &Args) -> CliResult {
+    let task = read_existing_task(parse_id(args.positional.first().map(String::as_str))?)?;
+    task.set_done(true);
+    update_task(&task)?;
+    for record in read_records(task.id(), true, None, None)? {
+        timer::stop_record(&record)?;
+    }
+    call_instance("ReloadTimer", None).unwrap_or(Ok(()))
+}
+
+fn timer(args: &Args) -> CliResult {
+    match args.positional.first().map(String::as_str) {
+        Some("start") => {
+            let task = read_existing_task(parse_id(args.positional.get(1).map(String::as_str))?)?;
+            match call_instance("StartTimer", Some(&(task.id(),).to_variant())) {
+                Some(result) => result?,
+                None => {
+                    timer::start(task.id())?;
+                }
+            }
+        }
+        Some("stop") => match call_instance("StopTimer", None) {
+            Some(result) => result?,
+            None => timer::stop()?,
+        },
+        Some("status") => {
+            let running = read_incomplete_records()?;
+            let json = args.flag("json");
+            if running.is_empty() && !json {
+                println!("{}", gettext("No timer is running"));
+            }
+            let mut lines = vec![];
+            for record in running {
+                let task = read_task(record.task())?;
+                let elapsed = now() - record.start();
+                if json {
+                    lines.push(format!(
+                        "{{\"task\": {}, \"name\": {}, \"start\": {}, \"elapsed\": {}}}",
+                        task.id(),
+                        json_string(&task.name()),
+                        record.start(),
+                        elapsed
+                    ));
+                } else {
+                    lines.push(format!(
+                        "{}\t{}\t{}",
+                        task.id(),
+                        task.name(),
+                        Record::duration_display(elapsed)
+                    ));
+                }
+            }
+            if json {
+                println!("[{}]", lines.join(",\n "));
+            } else {
+                for line in lines {
+                    println!("{line}");
+                }
+            }
+        }
+        _ => return Err(CliError::Usage(gettext("Use timer start, stop or status"))),
+    }
+    Ok(())
+}
+
+fn report(args: &Args) -> CliResult {
+    let days = if args.flag("day") {
+        1
+    } else if args.flag("month") {
+        30
+    } else {
+        7
+    };
+    let now = glib::DateTime::now_local().unwrap();
+    let start = glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+        .unwrap()
+        .add_days(1 - days)
+        .unwrap()
+        .to_unix();
+    let projects = match args.value("project") {
+        Some(name) => vec![find_project(name)?],
+        None => read_projects(true)?,
+    };
+    let json = args.flag("json");
+    let mut total = 0;
+    let mut project_entries = vec![];
+    for project in projects {
+        let mut project_duration = 0;
+        let mut task_entries = vec![];
+        for task in read_tasks(project.id(), None, None, None)? {
+            // Records starting exactly at the start of the period are included
+            let records = read_records(task.id(), false, Some(start - 1), None)?;
+            let duration: i64 = records.iter().map(Record::duration).sum();
+            if duration == 0 {
+                continue;
+            }
+            project_duration += duration;
+            let notes: Vec<String> = records
+                .iter()
+                .rev()
+                .map(Record::note)
+                .filter(|note| !note.is_empty())
+                .collect();
+            if json {
+                let notes: Vec<String> = notes.iter().map(|note| json_string(note)).collect();
+                task_entries.push(format!(
+                    "{{\"id\": {}, \"name\": {}, \"duration\": {duration}, \"notes\": [{}]}}",
+                    task.id(),
+                    json_string(&task.name()),
+                    notes.join(", ")
+                ));
+            } else {
+                task_entries.push(format!(
+                    "  {}\t{}",
+                    task.name(),
+                    Record::duration_display(duration)
+                ));
+                for note in notes {
+                    task_entries.push(format!("    - {note}"));
+                }
+            }
+        }
+        if project_duration == 0 {
+            continue;
+        }
+        total += project_duration;
+        if json {
+            project_entries.push(format!(
+                "{{\"id\": {}, \"name\": {}, \"duration\": {project_duration}, \"tasks\": [{}]}}",
+                project.id(),
+                json_string(&project.name()),
+                task_entries.join(", ")
+            ));
+        } else {
+            project_entries.push(format!(
+                "{}\t{}\n{}",
+                project.name(),
+                Record::duration_display(project_duration),
+                task_entries.join("\n")
+            ));
+        }
+    }
+    if json {
+        println!(
+            "{{\"start\": {start}, \"end\": {}, \"duration\": {total}, \"projects\": [{}]}}",
+            now.to_unix(),
+            project_entries.join(", ")
+        );
+    } else {
+        for entry in project_entries {
+            println!("{entry}");
+        }
+        println!("{}\t{}", gettext("Total"), Record::duration_display(total));
+    }
+    Ok(())
+}
@@ -1,13 +1,19 @@
 use gtk::glib;
 use rusqlite::{Connection, Result};
 use std::cmp::Ordering;
+use std::time::Duration;
 
 use crate::db::migrate::MIGRATIONS;
 
-const DB_VERSION: u8 = 5;
+const DB_VERSION: u8 = 17;
 
+// CalDAV sync writes from another thread, so connections wait for its locks instead of failing
 pub fn get_connection() -> Connection {
-    Connection::open(glib::user_data_dir().join("data.db")).expect("Failed connect to database")
+    let conn = Connection::open(glib::user_data_dir().join("data.db"))
+        .expect("Failed connect to database");
+    conn.busy_timeout(Duration::from_secs(10))
+        .expect("Failed to set database busy timeout");
+    conn
 }
 
 pub fn check_database() -> Result<()> {
@@ -26,6 +32,13 @@ pub fn check_database() -> Result<()> {
                 i           INTEGER NOT NULL,
                 icon        TEXT    NOT NULL DEFAULT '',
                 description TEXT    NOT NULL DEFAULT '',
+                rate        REAL    NOT NULL DEFAULT 0,
+                client      TEXT    NOT NULL DEFAULT '',
+                budget      INTEGER NOT NULL DEFAULT 0,
+                budget_period INTEGER NOT NULL DEFAULT 0,
+                goal        INTEGER NOT NULL DEFAULT 0,
+                goal_period INTEGER NOT NULL DEFAULT 0,
+                inbox       INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -37,6 +50,8 @@ pub fn check_database() -> Result<()> {
                 name      TEXT    NOT NULL,
                 project   INTEGER NOT NULL,
                 i         INTEGER NOT NULL,
+                budget    INTEGER NOT NULL DEFAULT 0,
+                budget_period INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -53,6 +68,12 @@ pub fn check_database() -> Result<()> {
                 suspended   INTEGER NOT NULL DEFAULT 0,
                 parent      INTEGER NOT NULL DEFAULT 0,
                 description TEXT    NOT NULL DEFAULT '',
+                priority    INTEGER NOT NULL DEFAULT 0,
+                rate        REAL    NOT NULL DEFAULT 0,
+                done_at     INTEGER NOT NULL DEFAULT 0,
+                due_date    INTEGER NOT NULL DEFAULT 0,
+                tags        TEXT    NOT NULL DEFAULT '',
+                estimate    INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -64,6 +85,46 @@ pub fn check_database() -> Result<()> {
                 start	  INTEGER NOT NULL,
                 duration  INTEGER NOT NULL DEFAULT 0,
                 task      INTEGER NOT NULL,
+                note      TEXT    NOT NULL DEFAULT '',
+                billable  INTEGER NOT NULL DEFAULT 1,
+                PRIMARY KEY(id AUTOINCREMENT)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE caldav_bindings (
+                project   INTEGER NOT NULL,
+                url       TEXT    NOT NULL,
+                username  TEXT    NOT NULL DEFAULT '',
+                secret_key TEXT   NOT NULL DEFAULT '',
+                policy    INTEGER NOT NULL DEFAULT 0,
+                last_sync INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(project)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE caldav_items (
+                task      INTEGER NOT NULL,
+                project   INTEGER NOT NULL,
+                uid       TEXT    NOT NULL,
+                href      TEXT    NOT NULL,
+                etag      TEXT    NOT NULL DEFAULT '',
+                state     TEXT    NOT NULL DEFAULT '',
+                PRIMARY KEY(task)
+            );",
+            (),
+        )?;
+
+        conn.execute(
+            "CREATE TABLE saved_searches (
+                id        INTEGER NOT NULL,
+                name      TEXT    NOT NULL,
+                text      TEXT    NOT NULL DEFAULT '',
+                done      INTEGER NOT NULL DEFAULT 0,
+                filter    INTEGER NOT NULL DEFAULT 0,
                 PRIMARY KEY(id AUTOINCREMENT)
             );",
             (),
@@ -1,8 +1,12 @@
+use gtk::glib;
 use rusqlite::Result;
 
+use crate::caldav::store_password;
 use crate::db::get_connection;
 
-pub static MIGRATIONS: [fn() -> Result<()>; 5] = [to1, to2, to3, to4, to5];
+pub static MIGRATIONS: [fn() -> Result<()>; 17] = [
+    to1, to2, to3, to4, to5, to6, to7, to8, to9, to10, to11, to12, to13, to14, to15, to16, to17,
+];
 
 fn to1() -> Result<()> {
     // Create records from duration column in tasks table and drop it.
@@ -97,3 +101,190 @@ fn to5() -> Result<()> {
     )?;
     Ok(())
 }
+
+fn to6() -> Result<()> {
+    // Add priority column to tasks table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD priority INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to7() -> Result<()> {
+    // Add tables for binding projects to CalDAV collections
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE caldav_bindings (
+            project   INTEGER NOT NULL,
+            url       TEXT    NOT NULL,
+            username  TEXT    NOT NULL DEFAULT '',
+            password  TEXT    NOT NULL DEFAULT '',
+            policy    INTEGER NOT NULL DEFAULT 0,
+            last_sync INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(project)
+        );",
+        (),
+    )?;
+    conn.execute(
+        "CREATE TABLE caldav_items (
+            task      INTEGER NOT NULL,
+            project   INTEGER NOT NULL,
+            uid       TEXT    NOT NULL,
+            href      TEXT    NOT NULL,
+            etag      TEXT    NOT NULL DEFAULT '',
+            state     TEXT    NOT NULL DEFAULT '',
+            PRIMARY KEY(task)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to8() -> Result<()> {
+    // Add note column to records table
+    let conn = get_connection();
+    conn.execute("ALTER TABLE records ADD note TEXT NOT NULL DEFAULT '';", ())?;
+    Ok(())
+}
+
+fn to9() -> Result<()> {
+    // Add billing columns, hourly rate and client to projects, rate to tasks
+    // and billable to records
+    let conn = get_connection();
+    conn.execute("ALTER TABLE projects ADD rate REAL NOT NULL DEFAULT 0;", ())?;
+    conn.execute(
+        "ALTER TABLE projects ADD client TEXT NOT NULL DEFAULT '';",
+        (),
+    )?;
+    conn.execute("ALTER TABLE tasks ADD rate REAL NOT NULL DEFAULT 0;", ())?;
+    conn.execute(
+        "ALTER TABLE records ADD billable INTEGER NOT NULL DEFAULT 1;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to10() -> Result<()> {
+    // Add time budget columns to projects and lists tables
+    let conn = get_connection();
+    for table in ["projects", "lists"] {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD budget INTEGER NOT NULL DEFAULT 0;"),
+            (),
+        )?;
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD budget_period INTEGER NOT NULL DEFAULT 0;"),
+            (),
+        )?;
+    }
+    Ok(())
+}
+
+fn to11() -> Result<()> {
+    // Add time goal columns to projects table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE projects ADD goal INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    conn.execute(
+        "ALTER TABLE projects ADD goal_period INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to12() -> Result<()> {
+    // Add completion time column to tasks table
+    // Done tasks get the end of their last record, or zero when they have no records
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD done_at INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    conn.execute(
+        "UPDATE tasks SET done_at = COALESCE(
+            (SELECT MAX(start + duration) FROM records WHERE task = tasks.id), 0)
+        WHERE done = 1",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to13() -> Result<()> {
+    // Add due date column to tasks table
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE tasks ADD due_date INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to14() -> Result<()> {
+    // Add table for saved searches
+    let conn = get_connection();
+    conn.execute(
+        "CREATE TABLE saved_searches (
+            id        INTEGER NOT NULL,
+            name      TEXT    NOT NULL,
+            text      TEXT    NOT NULL DEFAULT '',
+            done      INTEGER NOT NULL DEFAULT 0,
+            filter    INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(id AUTOINCREMENT)
+        );",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to15() -> Result<()> {
+    // Add inbox column to projects table, the inbox itself is created on first use
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE projects ADD inbox INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to16() -> Result<()> {
+    // Add tags and estimate columns to tasks table
+    let conn = get_connection();
+    conn.execute("ALTER TABLE tasks ADD tags TEXT NOT NULL DEFAULT '';", ())?;
+    conn.execute(
+        "ALTER TABLE tasks ADD estimate INTEGER NOT NULL DEFAULT 0;",
+        (),
+    )?;
+    Ok(())
+}
+
+fn to17() -> Result<()> {
+    // Move CalDAV passwords to the keyring, bindings keep the key of their password.
+    // A password that can't be stored is dropped and has to be entered again.
+    let conn = get_connection();
+    conn.execute(
+        "ALTER TABLE caldav_bindings RENAME COLUMN password TO secret_key;",
+        (),
+    )?;
+    let mut stmt = conn.prepare("SELECT project, url, secret_key FROM caldav_bindings")?;
+    let bindings = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<(i64, String, String)>>>()?;
+    for (project, url, password) in bindings {
+        let mut secret_key = String::new();
+        if !password.is_empty() {
+            let key = glib::uuid_string_random().to_string();
+            if store_password(&key, &url, &password).is_ok() {
+                secret_key = key;
+            }
+        }
+        conn.execute(
+            "UPDATE caldav_bindings SET secret_key = ?2 WHERE project = ?1",
+            (project, secret_key),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use gettextrs::gettext;
+use gtk::glib;
+
+// Time budgets of projects and lists, a budget of zero seconds means no budget
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BudgetPeriod {
+    #[default]
+    Total,
+    Week,
+    Month,
+}
+
+impl BudgetPeriod {
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => BudgetPeriod::Week,
+            2 => BudgetPeriod::Month,
+            _ => BudgetPeriod::Total,
+        }
+    }
+
+    // Start of the current period, the total budget counts all records
+    pub fn start(&self) -> Option<i64> {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        match self {
+            BudgetPeriod::Total => None,
+            BudgetPeriod::Week => Some(today.add_days(1 - now.day_of_week()).unwrap().to_unix()),
+            BudgetPeriod::Month => Some(
+                glib::DateTime::from_local(now.year(), now.month(), 1, 0, 0, 0.0)
+                    .unwrap()
+                    .to_unix(),
+            ),
+        }
+    }
+
+    // Used and total hours, like "12.5 of 40 hours this week"
+    pub fn usage_display(&self, used: i64, budget: i64) -> String {
+        let text = match self {
+            BudgetPeriod::Total => gettext("{used} of {budget} hours"),
+            BudgetPeriod::Week => gettext("{used} of {budget} hours this week"),
+            BudgetPeriod::Month => gettext("{used} of {budget} hours this month"),
+        };
+        text.replace("{used}", &format!("{:.1}", used as f64 / 3600.0))
+            .replace("{budget}", &format!("{}", budget as f64 / 3600.0))
+    }
+}
@@ -0,0 +1,103 @@
+use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use rusqlite::{Error, Result, Row};
+use std::cell::{Cell, RefCell};
+
+use crate::caldav::{clear_password, lookup_password, store_password};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, Properties)]
+    #[properties(wrapper_type=super::CalDavBinding)]
+    pub struct CalDavBinding {
+        #[property(get, set)]
+        pub project: Cell<i64>,
+        #[property(get, set)]
+        pub url: RefCell<String>,
+        #[property(get, set)]
+        pub username: RefCell<String>,
+        // Key of the password in the keyring, empty when there is no password
+        #[property(get, set)]
+        pub secret_key: RefCell<String>,
+        #[property(get, set)]
+        pub policy: Cell<u8>,
+        #[property(get, set)]
+        pub last_sync: Cell<i64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CalDavBinding {
+        const NAME: &'static str = "CalDavBinding";
+        type Type = super::CalDavBinding;
+    }
+
+    impl ObjectImpl for CalDavBinding {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct CalDavBinding(ObjectSubclass<imp::CalDavBinding>);
+}
+
+impl CalDavBinding {
+    pub fn new(
+        project: i64,
+        url: String,
+        username: String,
+        secret_key: String,
+        policy: u8,
+        last_sync: i64,
+    ) -> Self {
+        glib::Object::builder()
+            .property("project", project)
+            .property("url", url)
+            .property("username", username)
+            .property("secret-key", secret_key)
+            .property("policy", policy)
+            .property("last-sync", last_sync)
+            .build()
+    }
+
+    pub fn password(&self) -> Result<String, glib::Error> {
+        lookup_password(&self.secret_key())
+    }
+
+    // Only the key is saved in the database, so update the binding afterwards
+    pub fn set_password(&self, password: &str) -> Result<(), glib::Error> {
+        if password.is_empty() {
+            clear_password(&self.secret_key())?;
+            self.set_secret_key("");
+            return Ok(());
+        }
+        if self.secret_key().is_empty() {
+            self.set_secret_key(glib::uuid_string_random().to_string());
+        }
+        store_password(&self.secret_key(), &self.url(), password)
+    }
+}
+
+impl TryFrom<&Row<'_>> for CalDavBinding {
+    type Error = Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(CalDavBinding::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+        ))
+    }
+}
@@ -0,0 +1,84 @@
+use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use rusqlite::{Error, Result, Row};
+use std::cell::{Cell, RefCell};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, Properties)]
+    #[properties(wrapper_type=super::CalDavItem)]
+    pub struct CalDavItem {
+        #[property(get, set)]
+        pub task: Cell<i64>,
+        #[property(get, set)]
+        pub project: Cell<i64>,
+        #[property(get, set)]
+        pub uid: RefCell<String>,
+        #[property(get, set)]
+        pub href: RefCell<String>,
+        #[property(get, set)]
+        pub etag: RefCell<String>,
+        // The task as it was in the last sync, used for finding local changes
+        #[property(get, set)]
+        pub state: RefCell<String>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CalDavItem {
+        const NAME: &'static str = "CalDavItem";
+        type Type = super::CalDavItem;
+    }
+
+    impl ObjectImpl for CalDavItem {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct CalDavItem(ObjectSubclass<imp::CalDavItem>);
+}
+
+impl CalDavItem {
+    pub fn new(
+        task: i64,
+        project: i64,
+        uid: String,
+        href: String,
+        etag: String,
+        state: String,
+    ) -> Self {
+        glib::Object::builder()
+            .property("task", task)
+            .property("project", project)
+            .property("uid", uid)
+            .property("href", href)
+            .property("etag", etag)
+            .property("state", state)
+            .build()
+    }
+}
+
+impl TryFrom<&Row<'_>> for CalDavItem {
+    type Error = Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(CalDavItem::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+        ))
+    }
+}
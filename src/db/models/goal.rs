@@ -0,0 +1,120 @@
+use gettextrs::{gettext, ngettext};
+use gtk::{gio, glib, prelude::*};
+
+use crate::db::models::Project;
+use crate::db::operations::read_durations;
+
+// Tracked time targets, a goal of zero seconds means no goal
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GoalPeriod {
+    #[default]
+    Day,
+    Week,
+}
+
+impl GoalPeriod {
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => GoalPeriod::Week,
+            _ => GoalPeriod::Day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Goal {
+    pub target: i64,
+    pub period: GoalPeriod,
+    // None counts the records of all projects
+    pub project: Option<i64>,
+}
+
+impl Goal {
+    pub fn new(target: i64, period: GoalPeriod, project: Option<i64>) -> Self {
+        Self {
+            target,
+            period,
+            project,
+        }
+    }
+
+    // The goal from the preferences
+    pub fn global() -> Option<Self> {
+        let settings = gio::Settings::new("ir.imansalmani.IPlan.Preferences");
+        let target = (settings.double("goal-hours") * 3600.0) as i64;
+        let period = GoalPeriod::from_index(settings.uint("goal-period") as u8);
+        (target > 0).then(|| Self::new(target, period, None))
+    }
+
+    pub fn of_project(project: &Project) -> Option<Self> {
+        let period = GoalPeriod::from_index(project.goal_period());
+        (project.goal() > 0).then(|| Self::new(project.goal(), period, Some(project.id())))
+    }
+
+    // Start of the period that is `offset` periods before the current one
+    pub fn period_start(&self, offset: i32) -> glib::DateTime {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        match self.period {
+            GoalPeriod::Day => today.add_days(-offset).unwrap(),
+            GoalPeriod::Week => today
+                .add_days(1 - now.day_of_week())
+                .unwrap()
+                .add_weeks(-offset)
+                .unwrap(),
+        }
+    }
+
+    // Tracked time of the last periods, the current one comes first
+    pub fn history(&self, count: i32) -> Vec<(glib::DateTime, i64)> {
+        let mut history: Vec<(glib::DateTime, i64)> =
+            (0..count).map(|i| (self.period_start(i), 0)).collect();
+        let start = match history.last() {
+            Some((start, _)) => start.to_unix(),
+            None => return history,
+        };
+        for (record_start, duration) in
+            read_durations(self.project, start).expect("Failed to read durations")
+        {
+            if let Some((_, total)) = history
+                .iter_mut()
+                .find(|(period_start, _)| period_start.to_unix() <= record_start)
+            {
+                *total += duration;
+            }
+        }
+        history
+    }
+
+    // Reached periods in a row, the current period only counts when it is reached already
+    pub fn streak(&self) -> u32 {
+        let count = match self.period {
+            GoalPeriod::Day => 365,
+            GoalPeriod::Week => 52,
+        };
+        let history = self.history(count);
+        let mut periods = history.iter().map(|(_, tracked)| *tracked >= self.target);
+        let current = periods.next().unwrap_or(false);
+        periods.take_while(|reached| *reached).count() as u32 + current as u32
+    }
+
+    // Like "4 hours per day"
+    pub fn title(&self) -> String {
+        let hours = self.target as f64 / 3600.0;
+        let text = match self.period {
+            GoalPeriod::Day => gettext("{} hours per day"),
+            GoalPeriod::Week => gettext("{} hours per week"),
+        };
+        text.replace("{}", &hours.to_string())
+    }
+
+    pub fn streak_display(&self, streak: u32) -> String {
+        match self.period {
+            GoalPeriod::Day => ngettext("{} day streak", "{} days streak", streak),
+            GoalPeriod::Week => ngettext("{} week streak", "{} weeks streak", streak),
+        }
+        .replace("{}", &streak.to_string())
+    }
+}
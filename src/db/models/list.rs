@@ -2,6 +2,9 @@ use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use rusqlite::{Error, Result, Row};
 use std::cell::{Cell, RefCell};
 
+use crate::db::models::BudgetPeriod;
+use crate::db::operations::read_tasks;
+
 mod imp {
     use super::*;
 
@@ -16,6 +19,11 @@ mod imp {
         pub project: Cell<i64>,
         #[property(get, set)]
         pub index: Cell<i32>,
+        // Seconds, see BudgetPeriod
+        #[property(get, set)]
+        pub budget: Cell<i64>,
+        #[property(get, set)]
+        pub budget_period: Cell<u8>,
     }
 
     #[glib::object_subclass]
@@ -52,18 +60,29 @@ impl List {
             .property("index", index)
             .build()
     }
+
+    // Tracked time of the list tasks in the current budget period
+    pub fn budget_used(&self) -> i64 {
+        let start = BudgetPeriod::from_index(self.budget_period()).start();
+        read_tasks(self.project(), Some(self.id()), None, Some(0))
+            .expect("Failed to read tasks")
+            .iter()
+            .map(|task| match start {
+                Some(start) => task.duration_since(start),
+                None => task.duration(),
+            })
+            .sum()
+    }
 }
 
 impl TryFrom<&Row<'_>> for List {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(List::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            row.get(3)?,
-        ))
+        let list = List::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+        list.set_budget(row.get::<usize, i64>(4)?);
+        list.set_budget_period(row.get::<usize, u8>(5)?);
+        Ok(list)
     }
 }
 
@@ -9,3 +9,18 @@ pub use task::Task;
 
 mod record;
 pub use record::Record;
+
+mod budget;
+pub use budget::BudgetPeriod;
+
+mod goal;
+pub use goal::{Goal, GoalPeriod};
+
+mod saved_search;
+pub use saved_search::{SavedSearch, SearchFilter};
+
+mod caldav_binding;
+pub use caldav_binding::CalDavBinding;
+
+mod caldav_item;
+pub use caldav_item::CalDavItem;
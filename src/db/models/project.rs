@@ -1,8 +1,10 @@
+use gettextrs::gettext;
 use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use rusqlite::{Error, Result, Row};
 use std::cell::{Cell, RefCell};
 
-use crate::db::operations::read_tasks;
+use crate::db::models::BudgetPeriod;
+use crate::db::operations::{create_inbox, create_list, read_inbox, read_tasks};
 
 mod imp {
     use super::*;
@@ -22,6 +24,25 @@ mod imp {
         pub icon: RefCell<String>,
         #[property(get, set)]
         pub description: RefCell<String>,
+        // Hourly rate of the tracked time
+        #[property(get, set)]
+        pub rate: Cell<f64>,
+        // Invoice recipient, name and address
+        #[property(get, set)]
+        pub client: RefCell<String>,
+        // Seconds, see BudgetPeriod
+        #[property(get, set)]
+        pub budget: Cell<i64>,
+        #[property(get, set)]
+        pub budget_period: Cell<u8>,
+        // Seconds, see GoalPeriod
+        #[property(get, set)]
+        pub goal: Cell<i64>,
+        #[property(get, set)]
+        pub goal_period: Cell<u8>,
+        // Quick capture adds tasks here, it can't be deleted or archived
+        #[property(get, set)]
+        pub inbox: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -68,6 +89,16 @@ impl Project {
             .build()
     }
 
+    // The inbox is created on first use
+    pub fn inbox_project() -> Result<Self> {
+        if let Some(project) = read_inbox()? {
+            return Ok(project);
+        }
+        let project = create_inbox(&gettext("Inbox"))?;
+        create_list(&gettext("Tasks"), project.id())?;
+        Ok(project)
+    }
+
     pub fn duration(&self) -> i64 {
         let mut total = 0;
         for task in read_tasks(self.id(), None, None, Some(0)).expect("Failed to read tasks") {
@@ -75,20 +106,40 @@ impl Project {
         }
         total
     }
+
+    // Tracked time in the current budget period
+    pub fn budget_used(&self) -> i64 {
+        match BudgetPeriod::from_index(self.budget_period()).start() {
+            Some(start) => read_tasks(self.id(), None, None, Some(0))
+                .expect("Failed to read tasks")
+                .iter()
+                .map(|task| task.duration_since(start))
+                .sum(),
+            None => self.duration(),
+        }
+    }
 }
 
 impl TryFrom<&Row<'_>> for Project {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(Project::new(
+        let project = Project::new(
             row.get(0)?,
             row.get(1)?,
             row.get(2)?,
             row.get(3)?,
             row.get(4)?,
             row.get(5)?,
-        ))
+        );
+        project.set_rate(row.get::<usize, f64>(6)?);
+        project.set_client(row.get::<usize, String>(7)?);
+        project.set_budget(row.get::<usize, i64>(8)?);
+        project.set_budget_period(row.get::<usize, u8>(9)?);
+        project.set_goal(row.get::<usize, i64>(10)?);
+        project.set_goal_period(row.get::<usize, u8>(11)?);
+        project.set_inbox(row.get::<usize, bool>(12)?);
+        Ok(project)
     }
 }
 
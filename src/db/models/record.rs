@@ -1,6 +1,10 @@
 use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
 use rusqlite::{Error, Result, Row};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+
+use crate::db::operations::{
+    create_record, delete_record, find_overlapping_records, read_task, update_record,
+};
 
 mod imp {
     use super::*;
@@ -15,6 +19,10 @@ mod imp {
         pub duration: Cell<i64>, // FIXME: Cell<Option<i64>>, because of glib::value::get
         #[property(get, set)]
         pub task: Cell<i64>,
+        #[property(get, set)]
+        pub note: RefCell<String>,
+        #[property(get, set)]
+        pub billable: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -49,9 +57,98 @@ impl Record {
             .property("start", start)
             .property("duration", duration)
             .property("task", task)
+            .property("billable", true)
             .build()
     }
 
+    pub fn end(&self) -> i64 {
+        self.start() + self.duration()
+    }
+
+    // The record ends at the time and a new record continues from there
+    pub fn split(&self, at: i64) -> Result<Record> {
+        let rest = create_record(at, self.task(), self.end() - at, &self.note())?;
+        if !self.billable() {
+            rest.set_billable(false);
+            update_record(&rest)?;
+        }
+        self.set_duration(at - self.start());
+        update_record(self)?;
+        Ok(rest)
+    }
+
+    // True when each record starts before the earlier ones end, so merging adds no gap
+    pub fn touching(records: &[Record]) -> bool {
+        let mut records = records.to_vec();
+        records.sort_by_key(Record::start);
+        let mut end = match records.first() {
+            Some(record) => record.end(),
+            None => return true,
+        };
+        for record in &records[1..] {
+            if record.start() > end {
+                return false;
+            }
+            end = end.max(record.end());
+        }
+        true
+    }
+
+    // The earliest record spans all of them and collects their notes, the others are deleted
+    pub fn merge(records: &[Record]) -> Result<()> {
+        let mut records = records.to_vec();
+        records.sort_by_key(Record::start);
+        let (first, others) = match records.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        let mut end = first.end();
+        let mut notes = vec![];
+        for record in &records {
+            end = end.max(record.end());
+            let note = record.note();
+            if !note.is_empty() && !notes.contains(&note) {
+                notes.push(note);
+            }
+        }
+        first.set_duration(end - first.start());
+        first.set_note(notes.join("; "));
+        update_record(first)?;
+        for record in others {
+            delete_record(record.id())?;
+        }
+        Ok(())
+    }
+
+    // Overlapping records count the same time twice in the totals
+    pub fn overlaps(&self) -> Vec<Record> {
+        if self.duration() <= 0 {
+            return vec![];
+        }
+        find_overlapping_records(self.start(), self.end(), self.id())
+            .expect("Failed to read overlapping records")
+    }
+
+    // Task name and time range of each record, one per line
+    pub fn overlaps_display(records: &[Record]) -> String {
+        records
+            .iter()
+            .map(|record| {
+                let start = glib::DateTime::from_unix_local(record.start()).unwrap();
+                let end = glib::DateTime::from_unix_local(record.end()).unwrap();
+                let task_name = read_task(record.task())
+                    .map(|task| task.name())
+                    .unwrap_or_default();
+                format!(
+                    "{task_name} ({} – {})",
+                    start.format("%x %H:%M").unwrap(),
+                    end.format("%H:%M").unwrap()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
     pub fn duration_display(duration: i64) -> String {
         if duration == 0 {
             return String::new();
@@ -70,12 +167,10 @@ impl TryFrom<&Row<'_>> for Record {
     type Error = Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        Ok(Record::new(
-            row.get(0)?,
-            row.get(1)?,
-            row.get(2)?,
-            row.get(3)?,
-        ))
+        let record = Record::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
+        record.set_note(row.get::<usize, String>(4)?);
+        record.set_billable(row.get::<usize, bool>(5)?);
+        Ok(record)
     }
 }
 
@@ -0,0 +1,102 @@
+use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use rusqlite::{Error, Result, Row};
+use std::cell::{Cell, RefCell};
+
+// Extra conditions of a task search, the index is stored in the database
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SearchFilter {
+    #[default]
+    All,
+    Untracked,
+    Due,
+    Prioritized,
+}
+
+impl SearchFilter {
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            1 => SearchFilter::Untracked,
+            2 => SearchFilter::Due,
+            3 => SearchFilter::Prioritized,
+            _ => SearchFilter::All,
+        }
+    }
+
+    pub fn sql(&self) -> &'static str {
+        match self {
+            SearchFilter::All => "",
+            SearchFilter::Untracked => "AND id NOT IN (SELECT task FROM records)",
+            SearchFilter::Due => "AND due_date > 0",
+            SearchFilter::Prioritized => "AND priority > 0",
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, Properties)]
+    #[properties(wrapper_type=super::SavedSearch)]
+    pub struct SavedSearch {
+        #[property(get, set)]
+        pub id: Cell<i64>,
+        #[property(get, set)]
+        pub name: RefCell<String>,
+        #[property(get, set)]
+        pub text: RefCell<String>,
+        #[property(get, set)]
+        pub done: Cell<bool>,
+        #[property(get, set)]
+        pub filter: Cell<u8>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SavedSearch {
+        const NAME: &'static str = "SavedSearch";
+        type Type = super::SavedSearch;
+    }
+
+    impl ObjectImpl for SavedSearch {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec)
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SavedSearch(ObjectSubclass<imp::SavedSearch>);
+}
+
+impl SavedSearch {
+    pub fn new(id: i64, name: String, text: String, done: bool, filter: u8) -> Self {
+        glib::Object::builder()
+            .property("id", id)
+            .property("name", name)
+            .property("text", text)
+            .property("done", done)
+            .property("filter", filter)
+            .build()
+    }
+}
+
+impl TryFrom<&Row<'_>> for SavedSearch {
+    type Error = Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        Ok(SavedSearch::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+        ))
+    }
+}
@@ -3,7 +3,8 @@ use rusqlite::{Error, Result, Row};
 use std::cell::{Cell, RefCell};
 
 use crate::db::models::Record;
-use crate::db::operations::{read_records, read_tasks};
+use crate::db::operations::{read_project, read_records, read_tasks};
+use crate::timer::repair_incomplete_records;
 
 mod imp {
     use super::*;
@@ -29,6 +30,20 @@ mod imp {
         pub parent: Cell<i64>,
         #[property(get, set)]
         pub description: RefCell<String>,
+        #[property(get, set)]
+        pub priority: Cell<u8>,
+        // Zero uses the hourly rate of the project
+        #[property(get, set)]
+        pub rate: Cell<f64>,
+        // Start of the day in local time, zero means no due date
+        #[property(get, set)]
+        pub due_date: Cell<i64>,
+        // Separated by spaces, without the # sign
+        #[property(get, set)]
+        pub tags: RefCell<String>,
+        // Seconds, zero means no estimate
+        #[property(get, set)]
+        pub estimate: Cell<i64>,
     }
 
     #[glib::object_subclass]
@@ -76,23 +91,40 @@ impl Task {
         total
     }
 
+    // Tracked time of the task and its subtasks from start
+    pub fn duration_since(&self, start: i64) -> i64 {
+        let mut total: i64 = read_records(self.id(), false, Some(start - 1), None)
+            .expect("Failed to read records")
+            .iter()
+            .map(Record::duration)
+            .sum();
+        for subtask in read_tasks(self.project(), None, None, Some(self.id()))
+            .expect("Failed to read subtasks")
+        {
+            total += subtask.duration_since(start);
+        }
+        total
+    }
+
+    pub fn hourly_rate(&self) -> Result<f64> {
+        if self.rate() > 0.0 {
+            Ok(self.rate())
+        } else {
+            Ok(read_project(self.project())?.rate())
+        }
+    }
+
     pub fn duration_display(&self) -> String {
         Record::duration_display(self.duration())
     }
 
+    // Extra incomplete records are closed at the start of the newer one
     pub fn incomplete_record(&self) -> Option<Record> {
-        let incomplete_records =
-            read_records(self.id(), true, None, None).expect("Failed to read records");
-        match incomplete_records.len() {
-            0 => None,
-            1 => {
-                let record = incomplete_records.get(0).unwrap().to_owned();
-                record
-                    .set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-                Some(record)
-            }
-            _ => panic!("The Task cannot have multiple incomplete records"),
-        }
+        let record = read_records(self.id(), true, None, None)
+            .and_then(repair_incomplete_records)
+            .expect("Failed to repair incomplete records")?;
+        record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
+        Some(record)
     }
 }
 
@@ -110,6 +142,11 @@ impl TryFrom<&Row<'_>> for Task {
             ("suspended", &row.get::<usize, bool>(6)?),
             ("parent", &row.get::<usize, i64>(7)?),
             ("description", &row.get::<usize, String>(8)?),
+            ("priority", &row.get::<usize, u8>(9)?),
+            ("rate", &row.get::<usize, f64>(10)?),
+            ("due_date", &row.get::<usize, i64>(12)?),
+            ("tags", &row.get::<usize, String>(13)?),
+            ("estimate", &row.get::<usize, i64>(14)?),
         ]))
     }
 }
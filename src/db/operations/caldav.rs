@@ -0,0 +1,106 @@
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::{CalDavBinding, CalDavItem};
+
+pub fn create_caldav_binding(binding: &CalDavBinding) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT INTO caldav_bindings(project, url, username, secret_key, policy)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        (
+            binding.project(),
+            binding.url(),
+            binding.username(),
+            binding.secret_key(),
+            binding.policy(),
+        ),
+    )?;
+    Ok(())
+}
+
+pub fn read_caldav_bindings() -> Result<Vec<CalDavBinding>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM caldav_bindings")?;
+    let mut rows = stmt.query([])?;
+    let mut bindings = Vec::new();
+    while let Some(row) = rows.next()? {
+        bindings.push(CalDavBinding::try_from(row)?)
+    }
+    Ok(bindings)
+}
+
+pub fn read_caldav_binding(project_id: i64) -> Result<Option<CalDavBinding>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM caldav_bindings WHERE project = ?")?;
+    let mut rows = stmt.query([project_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(CalDavBinding::try_from(row)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn update_caldav_binding(binding: &CalDavBinding) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "UPDATE caldav_bindings SET
+        url = ?2, username = ?3, secret_key = ?4, policy = ?5, last_sync = ?6 WHERE project = ?1",
+        (
+            binding.project(),
+            binding.url(),
+            binding.username(),
+            binding.secret_key(),
+            binding.policy(),
+            binding.last_sync(),
+        ),
+    )?;
+    Ok(())
+}
+
+// The password is removed from the keyring too
+pub fn delete_caldav_binding(project_id: i64) -> Result<()> {
+    if let Some(binding) = read_caldav_binding(project_id)? {
+        let _ = binding.set_password("");
+    }
+    let conn = get_connection();
+    conn.execute(
+        "DELETE FROM caldav_bindings WHERE project = ?",
+        (project_id,),
+    )?;
+    conn.execute("DELETE FROM caldav_items WHERE project = ?", (project_id,))?;
+    Ok(())
+}
+
+pub fn create_caldav_item(item: &CalDavItem) -> Result<()> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT OR REPLACE INTO caldav_items(task, project, uid, href, etag, state)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            item.task(),
+            item.project(),
+            item.uid(),
+            item.href(),
+            item.etag(),
+            item.state(),
+        ),
+    )?;
+    Ok(())
+}
+
+pub fn read_caldav_items(project_id: i64) -> Result<Vec<CalDavItem>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM caldav_items WHERE project = ?")?;
+    let mut rows = stmt.query([project_id])?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        items.push(CalDavItem::try_from(row)?)
+    }
+    Ok(items)
+}
+
+pub fn delete_caldav_item(task_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM caldav_items WHERE task = ?", (task_id,))?;
+    Ok(())
+}
@@ -66,17 +66,35 @@ pub fn update_list(list: &List) -> Result<()> {
     conn.execute(
         &format!(
             "UPDATE lists SET
-            name = ?2, project = ?3, i = ?4 {index_stmt} WHERE id = ?1"
+            name = ?2, project = ?3, i = ?4, budget = ?5, budget_period = ?6
+            {index_stmt} WHERE id = ?1"
+        ),
+        (
+            list.id(),
+            list.name(),
+            list.project(),
+            list.index(),
+            list.budget(),
+            list.budget_period(),
         ),
-        (list.id(), list.name(), list.project(), list.index()),
     )?;
     Ok(())
 }
 
+// The last list of the inbox is kept, new tasks from quick add go there
 pub fn delete_list(list_id: i64) -> Result<()> {
     let conn = get_connection();
     // Notify: Not return error when id not exists
-    conn.execute("DELETE FROM lists WHERE id = ?", (list_id,))?;
+    let deleted = conn.execute(
+        "DELETE FROM lists WHERE id = ?1 AND NOT (
+            project IN (SELECT id FROM projects WHERE inbox = true)
+            AND (SELECT COUNT(*) FROM lists AS other WHERE other.project = lists.project) = 1
+        )",
+        (list_id,),
+    )?;
+    if deleted == 0 {
+        return Ok(());
+    }
     conn.execute("DELETE FROM tasks WHERE list = ?", (list_id,))?;
     Ok(())
 }
@@ -9,3 +9,9 @@ pub use task::*;
 
 mod record;
 pub use record::*;
+
+mod saved_search;
+pub use saved_search::*;
+
+mod caldav;
+pub use caldav::*;
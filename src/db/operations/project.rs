@@ -4,6 +4,7 @@ use rusqlite::Result;
 
 use crate::db::get_connection;
 use crate::db::models::Project;
+use crate::db::operations::delete_caldav_binding;
 
 pub fn create_project(name: &str) -> Result<Project> {
     let index = new_index();
@@ -22,6 +23,28 @@ pub fn create_project(name: &str) -> Result<Project> {
     ))
 }
 
+pub fn create_inbox(name: &str) -> Result<Project> {
+    let project = create_project(name)?;
+    let conn = get_connection();
+    conn.execute(
+        "UPDATE projects SET inbox = true, icon = '📥' WHERE id = ?",
+        (project.id(),),
+    )?;
+    project.set_inbox(true);
+    project.set_icon("📥");
+    Ok(project)
+}
+
+pub fn read_inbox() -> Result<Option<Project>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM projects WHERE inbox = true")?;
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(Project::try_from(row)?)),
+        None => Ok(None),
+    }
+}
+
 pub fn read_projects(archive: bool) -> Result<Vec<Project>> {
     let filters = if !archive {
         "WHERE archive = false"
@@ -73,7 +96,9 @@ pub fn update_project(project: &Project) -> Result<()> {
     conn.execute(
         &format!(
             "UPDATE projects SET
-            name = ?2, archive = ?3, icon = ?4, description = ?5 {index_stmt} WHERE id = ?1"
+            name = ?2, archive = ?3, icon = ?4, description = ?5,
+            rate = ?6, client = ?7, budget = ?8, budget_period = ?9,
+            goal = ?10, goal_period = ?11 {index_stmt} WHERE id = ?1"
         ),
         (
             project.id(),
@@ -81,17 +106,31 @@ pub fn update_project(project: &Project) -> Result<()> {
             project.archive(),
             project.icon(),
             project.description(),
+            project.rate(),
+            project.client(),
+            project.budget(),
+            project.budget_period(),
+            project.goal(),
+            project.goal_period(),
         ),
     )?;
     Ok(())
 }
 
+// The inbox is never deleted, quick add, the command line and D-Bus rely on it
 pub fn delete_project(project_id: i64, index: i32) -> Result<()> {
     let conn = get_connection();
     // Notify: Not return error when id not exists
-    conn.execute("DELETE FROM projects WHERE id = ?", (project_id,))?;
+    let deleted = conn.execute(
+        "DELETE FROM projects WHERE id = ? AND inbox = false",
+        (project_id,),
+    )?;
+    if deleted == 0 {
+        return Ok(());
+    }
     conn.execute("DELETE FROM lists WHERE project = ?", (project_id,))?;
     conn.execute("DELETE FROM tasks WHERE project = ?", (project_id,))?;
+    delete_caldav_binding(project_id)?;
     // Decrease upper projects index
     conn.execute("UPDATE projects SET i = i - 1 WHERE i > ?1", (index,))?;
     Ok(())
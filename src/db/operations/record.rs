@@ -3,18 +3,15 @@ use rusqlite::Result;
 use crate::db::get_connection;
 use crate::db::models::Record;
 
-pub fn create_record(start: i64, task_id: i64, duration: i64) -> Result<Record> {
+pub fn create_record(start: i64, task_id: i64, duration: i64, note: &str) -> Result<Record> {
     let conn = get_connection();
     conn.execute(
-        "INSERT INTO records(start, task, duration) VALUES (?1,?2,?3)",
-        (start, task_id, duration),
+        "INSERT INTO records(start, task, duration, note) VALUES (?1,?2,?3,?4)",
+        (start, task_id, duration, note),
     )?;
-    Ok(Record::new(
-        conn.last_insert_rowid(),
-        start,
-        duration,
-        task_id,
-    ))
+    let record = Record::new(conn.last_insert_rowid(), start, duration, task_id);
+    record.set_note(note);
+    Ok(record)
 }
 
 pub fn read_records(
@@ -47,6 +44,89 @@ pub fn read_records(
     Ok(records)
 }
 
+pub fn read_incomplete_records() -> Result<Vec<Record>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM records WHERE duration = 0 ORDER BY start DESC")?;
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next()? {
+        records.push(Record::try_from(row)?)
+    }
+    Ok(records)
+}
+
+// Finished records in all tasks that share some time with the range,
+// records left behind by deleted tasks are skipped
+pub fn find_overlapping_records(start: i64, end: i64, except_id: i64) -> Result<Vec<Record>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT records.* FROM records JOIN tasks ON tasks.id = records.task
+        WHERE records.duration > 0 AND records.id != ?3
+        AND records.start < ?2 AND records.start + records.duration > ?1
+        ORDER BY records.start",
+    )?;
+    let mut rows = stmt.query((start, end, except_id))?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next()? {
+        records.push(Record::try_from(row)?)
+    }
+    Ok(records)
+}
+
+// Pairs of overlapping records, the one that starts first comes first.
+// Records left behind by deleted tasks are skipped.
+pub fn read_overlapping_records() -> Result<Vec<(Record, Record)>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT first.id, second.id FROM records AS first JOIN records AS second
+        ON first.id != second.id
+        AND (first.start < second.start OR (first.start = second.start AND first.id < second.id))
+        AND second.start < first.start + first.duration
+        JOIN tasks AS first_task ON first_task.id = first.task
+        JOIN tasks AS second_task ON second_task.id = second.task
+        WHERE first.duration > 0 AND second.duration > 0 ORDER BY first.start DESC",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut pairs = Vec::new();
+    while let Some(row) = rows.next()? {
+        pairs.push((read_record(row.get(0)?)?, read_record(row.get(1)?)?))
+    }
+    Ok(pairs)
+}
+
+// Start and duration of finished records since the start, in one project or all of them
+pub fn read_durations(project_id: Option<i64>, start: i64) -> Result<Vec<(i64, i64)>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT records.start, records.duration FROM records
+        JOIN tasks ON tasks.id = records.task
+        WHERE records.duration > 0 AND records.start >= ?1
+        AND (?2 IS NULL OR tasks.project = ?2)",
+    )?;
+    let mut rows = stmt.query((start, project_id))?;
+    let mut durations = Vec::new();
+    while let Some(row) = rows.next()? {
+        durations.push((row.get(0)?, row.get(1)?))
+    }
+    Ok(durations)
+}
+
+// Project, start and duration of finished records since the start
+pub fn read_project_durations(start: i64) -> Result<Vec<(i64, i64, i64)>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.project, records.start, records.duration FROM records
+        JOIN tasks ON tasks.id = records.task
+        WHERE records.duration > 0 AND records.start >= ?1 ORDER BY records.start",
+    )?;
+    let mut rows = stmt.query([start])?;
+    let mut durations = Vec::new();
+    while let Some(row) = rows.next()? {
+        durations.push((row.get(0)?, row.get(1)?, row.get(2)?))
+    }
+    Ok(durations)
+}
+
 pub fn read_record(record_id: i64) -> Result<Record> {
     let conn = get_connection();
     let mut stmt = conn.prepare("SELECT * FROM records WHERE id = ?")?;
@@ -56,12 +136,15 @@ pub fn read_record(record_id: i64) -> Result<Record> {
 pub fn update_record(record: &Record) -> Result<()> {
     let conn = get_connection();
     conn.execute(
-        "UPDATE records SET start = ?2, duration = ?3, task = ?4 WHERE id = ?1",
+        "UPDATE records SET start = ?2, duration = ?3, task = ?4, note = ?5, billable = ?6
+        WHERE id = ?1",
         (
             record.id(),
             record.start(),
             record.duration(),
             record.task(),
+            record.note(),
+            record.billable(),
         ),
     )?;
     Ok(())
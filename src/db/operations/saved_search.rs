@@ -0,0 +1,42 @@
+use rusqlite::Result;
+
+use crate::db::get_connection;
+use crate::db::models::SavedSearch;
+
+pub fn create_saved_search(name: &str, text: &str, done: bool, filter: u8) -> Result<SavedSearch> {
+    let conn = get_connection();
+    conn.execute(
+        "INSERT INTO saved_searches(name, text, done, filter) VALUES (?1, ?2, ?3, ?4)",
+        (name, text, done, filter),
+    )?;
+    Ok(SavedSearch::new(
+        conn.last_insert_rowid(),
+        name.to_string(),
+        text.to_string(),
+        done,
+        filter,
+    ))
+}
+
+pub fn read_saved_searches() -> Result<Vec<SavedSearch>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM saved_searches ORDER BY id")?;
+    let mut rows = stmt.query([])?;
+    let mut searches = Vec::new();
+    while let Some(row) = rows.next()? {
+        searches.push(SavedSearch::try_from(row)?)
+    }
+    Ok(searches)
+}
+
+pub fn read_saved_search(search_id: i64) -> Result<SavedSearch> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT * FROM saved_searches WHERE id = ?")?;
+    stmt.query_row([search_id], |row| SavedSearch::try_from(row))
+}
+
+pub fn delete_saved_search(search_id: i64) -> Result<()> {
+    let conn = get_connection();
+    conn.execute("DELETE FROM saved_searches WHERE id = ?", [search_id])?;
+    Ok(())
+}
@@ -1,7 +1,8 @@
+use gtk::glib;
 use rusqlite::Result;
 
 use crate::db::get_connection;
-use crate::db::models::Task;
+use crate::db::models::{SearchFilter, Task};
 
 pub fn create_task(name: &str, project_id: i64, list_id: i64, parent: i64) -> Result<Task> {
     let position = new_position(list_id);
@@ -95,7 +96,10 @@ pub fn update_task(task: &Task) -> Result<()> {
         &format!(
             "UPDATE tasks SET
             name = ?2, done = ?3, project = ?4, list = ?5,
-            {position_stmt} suspended = ?6, parent = ?7, description = ?8 WHERE id = ?1"
+            {position_stmt} suspended = ?6, parent = ?7, description = ?8,
+            priority = ?9, rate = ?10, due_date = ?12, tags = ?13, estimate = ?14,
+            done_at = CASE WHEN NOT ?3 THEN 0 WHEN done_at = 0 THEN ?11 ELSE done_at END
+            WHERE id = ?1"
         ),
         (
             task.id(),
@@ -106,11 +110,62 @@ pub fn update_task(task: &Task) -> Result<()> {
             task.suspended(),
             task.parent(),
             task.description(),
+            task.priority(),
+            task.rate(),
+            glib::DateTime::now_local().unwrap().to_unix(),
+            task.due_date(),
+            task.tags(),
+            task.estimate(),
         ),
     )?;
     Ok(())
 }
 
+// Undone tasks of active projects that are due before the end, the most overdue come first
+pub fn read_due_tasks(end: i64) -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.* FROM tasks JOIN projects ON projects.id = tasks.project
+        WHERE tasks.done = false AND tasks.suspended = false AND projects.archive = false
+        AND tasks.due_date > 0 AND tasks.due_date < ? ORDER BY tasks.due_date",
+    )?;
+    let mut rows = stmt.query([end])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
+
+// Tasks of active projects that have records running or ending after the start
+pub fn read_tracked_tasks(start: i64) -> Result<Vec<Task>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare(
+        "SELECT tasks.* FROM tasks JOIN projects ON projects.id = tasks.project
+        WHERE tasks.suspended = false AND projects.archive = false AND tasks.id IN
+        (SELECT task FROM records WHERE duration = 0 OR start + duration > ?1)
+        ORDER BY tasks.project, tasks.position DESC",
+    )?;
+    let mut rows = stmt.query([start])?;
+    let mut tasks = Vec::new();
+    while let Some(row) = rows.next()? {
+        tasks.push(Task::try_from(row)?)
+    }
+    Ok(tasks)
+}
+
+// Completion times of the tasks done since the start
+pub fn read_done_times(start: i64) -> Result<Vec<i64>> {
+    let conn = get_connection();
+    let mut stmt = conn.prepare("SELECT done_at FROM tasks WHERE done = 1 AND done_at >= ?")?;
+    let mut rows = stmt.query([start])?;
+    let mut times = Vec::new();
+    while let Some(row) = rows.next()? {
+        times.push(row.get(0)?)
+    }
+    Ok(times)
+}
+
 pub fn delete_task(task_id: i64, list_id: i64, position: i32) -> Result<()> {
     let conn = get_connection();
     // Notify: Not return error when id not exists
@@ -125,14 +180,15 @@ pub fn delete_task(task_id: i64, list_id: i64, position: i32) -> Result<()> {
     Ok(())
 }
 
-pub fn find_tasks(text: &str, done: bool) -> Result<Vec<Task>> {
-    let filters = if done { "" } else { "AND done = false" };
+pub fn find_tasks(text: &str, done: bool, filter: SearchFilter) -> Result<Vec<Task>> {
+    let done_filter = if done { "" } else { "AND done = false" };
+    let filters = format!("{done_filter} {}", filter.sql());
     // Replace % and _ with \% and \_ because they have meaning
     // FIXME: do this without copy string
     let text = text.replace('%', r"\%").replace('_', r"\_");
     let conn = get_connection();
     let mut stmt = conn.prepare(&format!(
-        "SELECT * FROM tasks WHERE name LIKE ? ESCAPE '\\' {filters}"
+        "SELECT * FROM tasks WHERE (name LIKE ?1 ESCAPE '\\' OR tags LIKE ?1 ESCAPE '\\') {filters}"
     ))?;
     let mut rows = stmt.query([format!("%{text}%")])?;
     let mut tasks = Vec::new();
@@ -0,0 +1,238 @@
+use gtk::{gio, glib, prelude::*};
+use rusqlite::Result;
+
+use crate::application::IPlanApplication;
+use crate::config::APPLICATION_ID;
+use crate::db::models::{Project, SearchFilter};
+use crate::db::operations::{
+    create_task, find_tasks, read_lists, read_project, read_projects, read_task,
+};
+use crate::timer::TimerService;
+
+const INTERFACE_NAME: &str = "ir.imansalmani.IPlan1";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="ir.imansalmani.IPlan1">
+    <method name="StartTimer">
+      <arg type="x" name="task_id" direction="in" />
+    </method>
+    <method name="StopTimer" />
+    <method name="ReloadTimer" />
+    <method name="GetActiveTimer">
+      <arg type="b" name="running" direction="out" />
+      <arg type="x" name="task_id" direction="out" />
+      <arg type="s" name="task_name" direction="out" />
+      <arg type="x" name="start" direction="out" />
+    </method>
+    <method name="AddTask">
+      <arg type="s" name="project" direction="in" />
+      <arg type="s" name="list" direction="in" />
+      <arg type="s" name="name" direction="in" />
+      <arg type="x" name="task_id" direction="out" />
+    </method>
+    <method name="QuickAdd" />
+    <method name="Search">
+      <arg type="s" name="query" direction="in" />
+      <arg type="a(xssb)" name="tasks" direction="out" />
+    </method>
+    <signal name="TimerChanged">
+      <arg type="b" name="running" />
+      <arg type="x" name="task_id" />
+      <arg type="x" name="start" />
+    </signal>
+  </interface>
+</node>
+"#;
+
+enum MethodError {
+    NotFound(String),
+    InvalidArgs,
+    Database(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for MethodError {
+    fn from(error: rusqlite::Error) -> Self {
+        match error {
+            rusqlite::Error::QueryReturnedNoRows => MethodError::NotFound(error.to_string()),
+            error => MethodError::Database(error),
+        }
+    }
+}
+
+// The object path GApplication exports for the application id
+fn object_path() -> String {
+    format!("/{}", APPLICATION_ID.replace('.', "/").replace('-', "_"))
+}
+
+// Calls a method of the running instance, None when IPlan is not running
+pub fn call_instance(
+    method: &str,
+    parameters: Option<&glib::Variant>,
+) -> Option<std::result::Result<glib::Variant, glib::Error>> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok()?;
+    let (has_owner,) = connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            Some(&(APPLICATION_ID,).to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            gio::Cancellable::NONE,
+        )
+        .ok()?
+        .get::<(bool,)>()?;
+    if !has_owner {
+        return None;
+    }
+    Some(connection.call_sync(
+        Some(APPLICATION_ID),
+        &object_path(),
+        INTERFACE_NAME,
+        method,
+        parameters,
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+    ))
+}
+
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info.lookup_interface(INTERFACE_NAME).unwrap();
+    connection.register_object(
+        object_path,
+        &interface_info,
+        |_connection, _sender, _path, _interface, method, parameters, invocation| {
+            match handle_method(method, &parameters) {
+                Ok(value) => invocation.return_value(value.as_ref()),
+                Err(MethodError::NotFound(message)) => {
+                    invocation.return_dbus_error("ir.imansalmani.IPlan1.Error.NotFound", &message)
+                }
+                Err(MethodError::InvalidArgs) => invocation.return_dbus_error(
+                    "org.freedesktop.DBus.Error.InvalidArgs",
+                    "Invalid arguments",
+                ),
+                Err(MethodError::Database(error)) => invocation
+                    .return_dbus_error("ir.imansalmani.IPlan1.Error.Failed", &error.to_string()),
+            }
+        },
+        |_connection, _sender, _path, _interface, _property| unreachable!(),
+        |_connection, _sender, _path, _interface, _property, _value| false,
+    )
+}
+
+fn handle_method(
+    method: &str,
+    parameters: &glib::Variant,
+) -> std::result::Result<Option<glib::Variant>, MethodError> {
+    match method {
+        "StartTimer" => {
+            let (task_id,) = parameters.get::<(i64,)>().ok_or(MethodError::InvalidArgs)?;
+            read_task(task_id)?;
+            TimerService::default().start(task_id)?;
+            Ok(None)
+        }
+        "StopTimer" => {
+            TimerService::default().stop()?;
+            Ok(None)
+        }
+        "ReloadTimer" => {
+            TimerService::default().reload()?;
+            Ok(None)
+        }
+        "GetActiveTimer" => {
+            let value = match TimerService::default().record() {
+                Some(record) => {
+                    let task = read_task(record.task())?;
+                    (true, task.id(), task.name(), record.start())
+                }
+                None => (false, 0, String::new(), 0),
+            };
+            Ok(Some(value.to_variant()))
+        }
+        "AddTask" => {
+            let (project, list, name) = parameters
+                .get::<(String, String, String)>()
+                .ok_or(MethodError::InvalidArgs)?;
+            let task_id = add_task(&project, &list, &name)?;
+            Ok(Some((task_id,).to_variant()))
+        }
+        "QuickAdd" => {
+            if let Some(app) = gio::Application::default() {
+                app.activate_action("quick-add", None);
+            }
+            Ok(None)
+        }
+        "Search" => {
+            let (query,) = parameters
+                .get::<(String,)>()
+                .ok_or(MethodError::InvalidArgs)?;
+            let mut tasks = vec![];
+            for task in find_tasks(&query, true, SearchFilter::All)? {
+                let project = read_project(task.project())?;
+                tasks.push((task.id(), task.name(), project.name(), task.done()));
+            }
+            Ok(Some((tasks,).to_variant()))
+        }
+        _ => Err(MethodError::InvalidArgs),
+    }
+}
+
+// Empty project and list names choose the inbox and its first list
+fn add_task(project: &str, list: &str, name: &str) -> std::result::Result<i64, MethodError> {
+    if name.trim().is_empty() {
+        return Err(MethodError::InvalidArgs);
+    }
+    let project = if project.is_empty() {
+        Project::inbox_project()?
+    } else {
+        read_projects(false)?
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(project))
+            .ok_or_else(|| MethodError::NotFound(format!("Project \"{project}\" not found")))?
+    };
+    let list = read_lists(project.id())?
+        .into_iter()
+        .find(|l| list.is_empty() || l.name().eq_ignore_ascii_case(list))
+        .ok_or_else(|| MethodError::NotFound(format!("List \"{list}\" not found")))?;
+    let task = create_task(name.trim(), project.id(), list.id(), 0)?;
+    reload_windows();
+    Ok(task.id())
+}
+
+// Task rows are created again for tasks added from outside
+fn reload_windows() {
+    if let Some(app) = gio::Application::default().and_downcast::<IPlanApplication>() {
+        app.reload_windows();
+    }
+}
+
+pub fn emit_timer_changed() {
+    let app = match gio::Application::default() {
+        Some(app) => app,
+        None => return,
+    };
+    let (connection, object_path) = match (app.dbus_connection(), app.dbus_object_path()) {
+        (Some(connection), Some(object_path)) => (connection, object_path),
+        _ => return,
+    };
+    let parameters = match TimerService::default().record() {
+        Some(record) => (true, record.task(), record.start()),
+        None => (false, 0, 0),
+    };
+    let _ = connection.emit_signal(
+        None,
+        &object_path,
+        INTERFACE_NAME,
+        "TimerChanged",
+        Some(&parameters.to_variant()),
+    );
+}
@@ -0,0 +1,405 @@
+use gtk::glib;
+use rusqlite::Result;
+use std::collections::HashMap;
+
+use crate::db::models::{List, Project, Record, Task};
+use crate::db::operations::{create_task, read_records, read_tasks, update_task};
+
+const PRODID: &str = "-//Iman Salmani//IPlan//EN";
+const UID_DOMAIN: &str = "ir.imansalmani.IPlan";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+#[derive(Debug, Default)]
+pub struct VTodo {
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub completed: bool,
+    pub priority: u8,
+    pub parent_uid: Option<String>,
+    // Start of the day in local time, zero means no due date
+    pub due_date: i64,
+    pub tags: Vec<String>,
+}
+
+pub fn task_uid(task_id: i64) -> String {
+    format!("task-{task_id}@{UID_DOMAIN}")
+}
+
+fn record_uid(record_id: i64) -> String {
+    format!("record-{record_id}@{UID_DOMAIN}")
+}
+
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(next) => unescaped.push(next),
+                None => {}
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+// Lines longer than 75 octets should be split, continuation lines start with a space
+pub fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        if let Some(continuation) = line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+pub fn parse_property(line: &str) -> Option<Property> {
+    // The value starts after the first colon that is not inside a quoted parameter
+    let mut in_quotes = false;
+    let mut split_at = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => {
+                split_at = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (head, value) = line.split_at(split_at?);
+    let mut head = head.split(';');
+    let name = head.next()?.to_uppercase();
+    let params = head
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value[1..].to_string(),
+    })
+}
+
+pub fn format_datetime(unix: i64) -> String {
+    glib::DateTime::from_unix_utc(unix)
+        .unwrap()
+        .format("%Y%m%dT%H%M%SZ")
+        .unwrap()
+        .to_string()
+}
+
+// Due dates are whole days in local time
+fn format_date(unix: i64) -> String {
+    glib::DateTime::from_unix_local(unix)
+        .unwrap()
+        .format("%Y%m%d")
+        .unwrap()
+        .to_string()
+}
+
+// Accepts dates and date-times, a date-time counts for its day in local time
+fn parse_date(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<i32>().ok();
+    let (year, month, day) = (number(0..4)?, number(4..6)?, number(6..8)?);
+    let date = if value.len() == 16 && value.ends_with('Z') {
+        let (hour, minute, second) = (number(9..11)?, number(11..13)?, number(13..15)?);
+        glib::DateTime::from_utc(year, month, day, hour, minute, second as f64)
+            .ok()?
+            .to_local()
+            .ok()?
+    } else {
+        glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()?
+    };
+    glib::DateTime::from_local(date.year(), date.month(), date.day_of_month(), 0, 0, 0.0)
+        .ok()
+        .map(|date| date.to_unix())
+}
+
+// Values of list properties like CATEGORIES are separated by unescaped commas
+fn split_values(value: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => values.push(unescape(&std::mem::take(&mut current))),
+            _ => current.push(c),
+        }
+    }
+    values.push(unescape(&current));
+    values
+}
+
+// iCalendar priority is 1 (highest) to 9 (lowest) and 0 for undefined
+pub fn priority_to_ical(priority: u8) -> u8 {
+    match priority {
+        3 => 1,
+        2 => 5,
+        1 => 9,
+        _ => 0,
+    }
+}
+
+pub fn priority_from_ical(priority: u8) -> u8 {
+    match priority {
+        1..=4 => 3,
+        5 => 2,
+        6..=9 => 1,
+        _ => 0,
+    }
+}
+
+// DTSTAMP is omitted when the output is only used for comparison
+pub fn format_vtodo(
+    task: &Task,
+    uid: &str,
+    parent_uid: Option<&str>,
+    dtstamp: Option<&str>,
+) -> String {
+    let mut component = String::from("BEGIN:VTODO\r\n");
+    component.push_str(&fold(&format!("UID:{uid}")));
+    if let Some(dtstamp) = dtstamp {
+        component.push_str(&fold(&format!("DTSTAMP:{dtstamp}")));
+    }
+    component.push_str(&fold(&format!("SUMMARY:{}", escape(&task.name()))));
+    let description = task.description();
+    if !description.is_empty() {
+        component.push_str(&fold(&format!("DESCRIPTION:{}", escape(&description))));
+    }
+    if task.done() {
+        component.push_str("STATUS:COMPLETED\r\n");
+    } else {
+        component.push_str("STATUS:NEEDS-ACTION\r\n");
+    }
+    let priority = priority_to_ical(task.priority());
+    if priority != 0 {
+        component.push_str(&format!("PRIORITY:{priority}\r\n"));
+    }
+    if task.due_date() > 0 {
+        component.push_str(&format!(
+            "DUE;VALUE=DATE:{}\r\n",
+            format_date(task.due_date())
+        ));
+    }
+    let tags = task.tags();
+    if !tags.is_empty() {
+        let categories: Vec<String> = tags.split_whitespace().map(escape).collect();
+        component.push_str(&fold(&format!("CATEGORIES:{}", categories.join(","))));
+    }
+    if let Some(parent_uid) = parent_uid {
+        component.push_str(&fold(&format!("RELATED-TO:{parent_uid}")));
+    }
+    component.push_str("END:VTODO\r\n");
+    component
+}
+
+fn format_vevent(record: &Record, task: &Task, dtstamp: &str) -> String {
+    let mut component = String::from("BEGIN:VEVENT\r\n");
+    component.push_str(&fold(&format!("UID:{}", record_uid(record.id()))));
+    component.push_str(&fold(&format!("DTSTAMP:{dtstamp}")));
+    component.push_str(&format!("DTSTART:{}\r\n", format_datetime(record.start())));
+    component.push_str(&format!(
+        "DTEND:{}\r\n",
+        format_datetime(record.start() + record.duration())
+    ));
+    component.push_str(&fold(&format!("SUMMARY:{}", escape(&task.name()))));
+    let note = record.note();
+    if !note.is_empty() {
+        component.push_str(&fold(&format!("DESCRIPTION:{}", escape(&note))));
+    }
+    component.push_str(&fold(&format!("RELATED-TO:{}", task_uid(task.id()))));
+    component.push_str("END:VEVENT\r\n");
+    component
+}
+
+pub fn wrap_calendar(components: &str) -> String {
+    let mut calendar = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n");
+    calendar.push_str(&format!("PRODID:{PRODID}\r\n"));
+    calendar.push_str(components);
+    calendar.push_str("END:VCALENDAR\r\n");
+    calendar
+}
+
+// Tasks are exported as VTODOs and their finished records as VEVENTs, running ones have no end yet
+pub fn export_project(project: &Project) -> Result<String> {
+    let dtstamp = format_datetime(glib::DateTime::now_utc().unwrap().to_unix());
+    let mut components = String::new();
+    for task in read_tasks(project.id(), None, None, None)? {
+        let parent_uid = (task.parent() != 0).then(|| task_uid(task.parent()));
+        components.push_str(&format_vtodo(
+            &task,
+            &task_uid(task.id()),
+            parent_uid.as_deref(),
+            Some(&dtstamp),
+        ));
+        for record in read_records(task.id(), false, None, None)? {
+            components.push_str(&format_vevent(&record, &task, &dtstamp));
+        }
+    }
+    Ok(wrap_calendar(&components))
+}
+
+pub fn parse_vtodos(text: &str) -> Vec<VTodo> {
+    let mut vtodos = vec![];
+    let mut components: Vec<String> = vec![];
+    let mut vtodo = VTodo::default();
+    for line in unfold(text) {
+        let property = match parse_property(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match property.name.as_str() {
+            "BEGIN" => {
+                if property.value.eq_ignore_ascii_case("VTODO") {
+                    vtodo = VTodo::default();
+                }
+                components.push(property.value.to_uppercase());
+            }
+            "END" => {
+                let component = components.pop();
+                if component.as_deref() == Some("VTODO") {
+                    vtodos.push(std::mem::take(&mut vtodo));
+                }
+            }
+            // Skip properties of nested components like VALARM
+            _ if components.last().map(String::as_str) != Some("VTODO") => {}
+            "UID" => vtodo.uid = property.value,
+            "SUMMARY" => vtodo.summary = unescape(&property.value),
+            "DESCRIPTION" => vtodo.description = unescape(&property.value),
+            "STATUS" => vtodo.completed = property.value.eq_ignore_ascii_case("COMPLETED"),
+            "COMPLETED" => vtodo.completed = true,
+            "PRIORITY" => {
+                vtodo.priority = priority_from_ical(property.value.trim().parse().unwrap_or(0))
+            }
+            "DUE" => vtodo.due_date = parse_date(&property.value).unwrap_or(0),
+            // Tags can't contain spaces
+            "CATEGORIES" => vtodo.tags.extend(
+                split_values(&property.value)
+                    .iter()
+                    .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+                    .filter(|tag| !tag.is_empty()),
+            ),
+            "RELATED-TO" => {
+                let is_parent = !property
+                    .params
+                    .iter()
+                    .any(|(key, value)| key == "RELTYPE" && !value.eq_ignore_ascii_case("PARENT"));
+                if is_parent {
+                    vtodo.parent_uid = Some(property.value);
+                }
+            }
+            _ => {}
+        }
+    }
+    vtodos
+}
+
+fn import_vtodo(
+    index: usize,
+    vtodos: &[VTodo],
+    uids: &HashMap<&str, usize>,
+    created: &mut HashMap<usize, i64>,
+    list: &List,
+) -> Result<i64> {
+    if let Some(task_id) = created.get(&index) {
+        return Ok(*task_id);
+    }
+    let vtodo = &vtodos[index];
+    // Create the parent first, subtasks don't belong to a list
+    let parent = vtodo
+        .parent_uid
+        .as_deref()
+        .and_then(|uid| uids.get(uid))
+        .filter(|parent_index| **parent_index != index);
+    let parent_id = if let Some(parent_index) = parent {
+        // Mark as visited to break RELATED-TO cycles
+        created.insert(index, 0);
+        import_vtodo(*parent_index, vtodos, uids, created, list)?
+    } else {
+        0
+    };
+    let task = if parent_id != 0 {
+        create_task(&vtodo.summary, list.project(), 0, parent_id)?
+    } else {
+        create_task(&vtodo.summary, list.project(), list.id(), 0)?
+    };
+    task.set_description(vtodo.description.clone());
+    task.set_done(vtodo.completed);
+    task.set_priority(vtodo.priority);
+    task.set_due_date(vtodo.due_date);
+    task.set_tags(vtodo.tags.join(" "));
+    update_task(&task)?;
+    created.insert(index, task.id());
+    Ok(task.id())
+}
+
+pub fn import(text: &str, list: &List) -> Result<usize> {
+    let vtodos = parse_vtodos(text);
+    let uids: HashMap<&str, usize> = vtodos
+        .iter()
+        .enumerate()
+        .filter(|(_, vtodo)| !vtodo.uid.is_empty())
+        .map(|(i, vtodo)| (vtodo.uid.as_str(), i))
+        .collect();
+    let mut created = HashMap::new();
+    // Tasks are shown by descending position so the first one should be created last
+    for index in (0..vtodos.len()).rev() {
+        import_vtodo(index, &vtodos, &uids, &mut created, list)?;
+    }
+    Ok(vtodos.len())
+}
@@ -0,0 +1,232 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::Result;
+
+use crate::db::models::{Project, Record};
+use crate::db::operations::{read_records, read_tasks};
+use crate::exchange::markup::{escape, DIM_COLOR};
+
+// Billed time of a task in the invoice period, money is in cents
+#[derive(Clone)]
+pub struct InvoiceItem {
+    pub name: String,
+    pub duration: i64,
+    pub rate: i64,
+}
+
+impl InvoiceItem {
+    // Rounded to the nearest cent, so the total is the sum of the printed lines
+    pub fn amount(&self) -> i64 {
+        (self.duration * self.rate + 1800) / 3600
+    }
+}
+
+#[derive(Clone)]
+pub struct Invoice {
+    pub project: String,
+    pub number: String,
+    pub sender: String,
+    pub client: String,
+    pub currency: String,
+    pub start: glib::DateTime,
+    // Last day of the period
+    pub end: glib::DateTime,
+    pub items: Vec<InvoiceItem>,
+}
+
+impl Invoice {
+    // Billable records of the days from start to end, with the time of each task
+    // rounded up to a multiple of rounding minutes
+    pub fn new(
+        project: &Project,
+        start: glib::DateTime,
+        end: glib::DateTime,
+        rounding: i64,
+    ) -> Result<Self> {
+        let period_end = end.add_days(1).unwrap().to_unix();
+        let mut items = vec![];
+        for task in read_tasks(project.id(), None, None, None)? {
+            let duration: i64 = read_records(
+                task.id(),
+                false,
+                Some(start.to_unix() - 1),
+                Some(period_end),
+            )?
+            .iter()
+            .filter(|record| record.billable())
+            .map(Record::duration)
+            .sum();
+            if duration == 0 {
+                continue;
+            }
+            let duration = if rounding > 0 {
+                let step = rounding * 60;
+                (duration + step - 1) / step * step
+            } else {
+                duration
+            };
+            items.push(InvoiceItem {
+                name: task.name(),
+                duration,
+                rate: (task.hourly_rate()? * 100.0).round() as i64,
+            });
+        }
+        Ok(Invoice {
+            project: project.name(),
+            number: String::new(),
+            sender: String::new(),
+            client: project.client(),
+            currency: String::new(),
+            start,
+            end,
+            items,
+        })
+    }
+
+    pub fn duration(&self) -> i64 {
+        self.items.iter().map(|item| item.duration).sum()
+    }
+
+    pub fn total(&self) -> i64 {
+        self.items.iter().map(InvoiceItem::amount).sum()
+    }
+
+    pub fn amount_display(&self, cents: i64) -> String {
+        format!("{}.{:02} {}", cents / 100, cents % 100, self.currency)
+            .trim()
+            .to_string()
+    }
+
+    pub fn file_name(&self, extension: &str) -> String {
+        // Translators: {} Will be replaced with the invoice number.
+        let name = gettext("Invoice {}").replace("{}", &self.number);
+        format!("{} {}.{extension}", name.trim(), self.project)
+    }
+
+    fn period_display(&self) -> String {
+        format!(
+            "{} – {}",
+            self.start.format("%x").unwrap(),
+            self.end.format("%x").unwrap()
+        )
+    }
+
+    fn title(&self) -> String {
+        gettext("Invoice {}")
+            .replace("{}", &self.number)
+            .trim()
+            .to_string()
+    }
+
+    // Pango markup for printing
+    pub fn markup(&self) -> String {
+        let mut markup = format!(
+            "<span size=\"xx-large\" weight=\"bold\">{}</span>\n",
+            escape(&self.title())
+        );
+        let today = glib::DateTime::now_local().unwrap();
+        markup.push_str(&format!(
+            "<span foreground=\"{DIM_COLOR}\">{}\t{}</span>\n",
+            escape(&self.project),
+            today.format("%x").unwrap()
+        ));
+        for (heading, text) in [
+            (gettext("From"), &self.sender),
+            (gettext("To"), &self.client),
+        ] {
+            if text.trim().is_empty() {
+                continue;
+            }
+            markup.push_str(&format!("\n<b>{}</b>\n", escape(&heading)));
+            for line in text.trim().lines() {
+                markup.push_str(&format!("{}\n", escape(line)));
+            }
+        }
+        markup.push_str(&format!(
+            "\n<span size=\"x-large\" weight=\"bold\">{}</span>\n",
+            escape(&gettext("Services"))
+        ));
+        markup.push_str(&format!(
+            "<span foreground=\"{DIM_COLOR}\">{}</span>\n",
+            self.period_display()
+        ));
+        for item in &self.items {
+            markup.push_str(&format!(
+                "{}\t{}\n     <span size=\"small\" foreground=\"{DIM_COLOR}\">{} × {}</span>\n",
+                escape(&item.name),
+                escape(&self.amount_display(item.amount())),
+                hours_display(item.duration),
+                escape(&self.amount_display(item.rate))
+            ));
+        }
+        markup.push_str(&format!(
+            "\n<b>{}\t{}</b>\n",
+            escape(&gettext("Total")),
+            escape(&self.amount_display(self.total()))
+        ));
+        markup
+    }
+
+    pub fn html(&self) -> String {
+        let title = escape(&self.title());
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+            <style>\nbody {{ font-family: sans-serif; max-width: 48em; margin: 2em auto; }}\n\
+            table {{ width: 100%; border-collapse: collapse; }}\n\
+            th, td {{ padding: 0.4em; border-bottom: 1px solid #deddda; text-align: left; }}\n\
+            .number {{ text-align: right; }}\n.dim {{ color: {DIM_COLOR}; }}\n\
+            .party {{ white-space: pre-line; }}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+        );
+        let today = glib::DateTime::now_local().unwrap();
+        html.push_str(&format!(
+            "<p class=\"dim\">{} · {}</p>\n",
+            escape(&self.project),
+            today.format("%x").unwrap()
+        ));
+        for (heading, text) in [
+            (gettext("From"), &self.sender),
+            (gettext("To"), &self.client),
+        ] {
+            if text.trim().is_empty() {
+                continue;
+            }
+            html.push_str(&format!(
+                "<h3>{}</h3>\n<p class=\"party\">{}</p>\n",
+                escape(&heading),
+                escape(text.trim())
+            ));
+        }
+        html.push_str(&format!(
+            "<h2>{}</h2>\n<p class=\"dim\">{}</p>\n<table>\n<tr><th>{}</th>\
+            <th class=\"number\">{}</th><th class=\"number\">{}</th>\
+            <th class=\"number\">{}</th></tr>\n",
+            escape(&gettext("Services")),
+            self.period_display(),
+            escape(&gettext("Task")),
+            escape(&gettext("Hours")),
+            escape(&gettext("Rate")),
+            escape(&gettext("Amount"))
+        ));
+        for item in &self.items {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td>\
+                <td class=\"number\">{}</td></tr>\n",
+                escape(&item.name),
+                hours_display(item.duration),
+                escape(&self.amount_display(item.rate)),
+                escape(&self.amount_display(item.amount()))
+            ));
+        }
+        html.push_str(&format!(
+            "<tr><th colspan=\"3\">{}</th><th class=\"number\">{}</th></tr>\n</table>\n\
+            </body>\n</html>\n",
+            escape(&gettext("Total")),
+            escape(&self.amount_display(self.total()))
+        ));
+        html
+    }
+}
+
+pub fn hours_display(duration: i64) -> String {
+    format!("{:.2}", duration as f64 / 3600.0)
+}
@@ -0,0 +1,8 @@
+use gtk::glib;
+
+// Color of secondary text in printed and exported documents
+pub const DIM_COLOR: &str = "#5e5c64";
+
+pub fn escape(text: &str) -> String {
+    glib::markup_escape_text(text).to_string()
+}
@@ -0,0 +1,33 @@
+pub mod icalendar;
+pub mod invoice;
+pub mod markup;
+pub mod outline;
+pub mod todo_txt;
+
+use outline::OutlineFormat;
+
+// Formats of the project export action, named like the menu targets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    TodoTxt,
+    ICalendar,
+    Outline(OutlineFormat),
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "todo-txt" => Some(ExportFormat::TodoTxt),
+            "icalendar" => Some(ExportFormat::ICalendar),
+            _ => OutlineFormat::from_name(name).map(ExportFormat::Outline),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::TodoTxt => "txt",
+            ExportFormat::ICalendar => "ics",
+            ExportFormat::Outline(format) => format.extension(),
+        }
+    }
+}
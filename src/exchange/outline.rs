@@ -0,0 +1,167 @@
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::Result;
+
+use crate::db::models::{Project, Record, Task};
+use crate::db::operations::{read_lists, read_projects, read_records, read_tasks};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineFormat {
+    Markdown,
+    Org,
+}
+
+impl OutlineFormat {
+    // Names used by the export actions
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "markdown" => Some(OutlineFormat::Markdown),
+            "org" => Some(OutlineFormat::Org),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutlineFormat::Markdown => "md",
+            OutlineFormat::Org => "org",
+        }
+    }
+
+    fn heading(&self, level: usize, text: &str) -> String {
+        let marker = match self {
+            OutlineFormat::Markdown => "#",
+            OutlineFormat::Org => "*",
+        };
+        format!("{} {text}\n\n", marker.repeat(level))
+    }
+
+    // Org tasks are headings with TODO keywords, so they can have a property drawer
+    fn task_line(&self, depth: usize, done: bool, name: &str) -> String {
+        match (self, done) {
+            (OutlineFormat::Markdown, false) => format!("{}- [ ] {name}\n", "  ".repeat(depth)),
+            (OutlineFormat::Markdown, true) => format!("{}- [x] {name}\n", "  ".repeat(depth)),
+            (OutlineFormat::Org, false) => format!("{} TODO {name}\n", "*".repeat(depth + 3)),
+            (OutlineFormat::Org, true) => format!("{} DONE {name}\n", "*".repeat(depth + 3)),
+        }
+    }
+
+    fn body_indent(&self, depth: usize) -> String {
+        match self {
+            OutlineFormat::Markdown => "  ".repeat(depth + 1),
+            OutlineFormat::Org => String::new(),
+        }
+    }
+
+    // Org keeps it in the property drawer, which has to follow the heading line
+    fn tracked_time(&self, duration: i64) -> String {
+        match self {
+            OutlineFormat::Markdown => format!(
+                "_{}: {}_",
+                gettext("Tracked time"),
+                Record::duration_display(duration)
+            ),
+            OutlineFormat::Org => format!(
+                ":PROPERTIES:\n:TRACKED_TIME: {}\n:END:",
+                Record::duration_display(duration)
+            ),
+        }
+    }
+
+    fn record_note(&self, record: &Record) -> String {
+        let start = glib::DateTime::from_unix_local(record.start()).unwrap();
+        let date = match self {
+            OutlineFormat::Markdown => start.format("%Y-%m-%d %H:%M").unwrap().to_string(),
+            OutlineFormat::Org => start.format("- [%Y-%m-%d %a %H:%M]").unwrap().to_string(),
+        };
+        format!(
+            "{date} ({}): {}",
+            Record::duration_display(record.duration()),
+            record.note()
+        )
+    }
+}
+
+// Indented lines stay part of the list item above them
+fn push_body(document: &mut String, text: &str, indent: &str) {
+    for line in text.lines() {
+        if !line.trim().is_empty() {
+            document.push_str(indent);
+            document.push_str(line);
+        }
+        document.push('\n');
+    }
+}
+
+fn push_task(
+    document: &mut String,
+    task: &Task,
+    format: OutlineFormat,
+    depth: usize,
+) -> Result<()> {
+    document.push_str(&format.task_line(depth, task.done(), &task.name()));
+    let body_indent = format.body_indent(depth);
+    let duration = task.duration();
+    if duration != 0 && format == OutlineFormat::Org {
+        push_body(document, &format.tracked_time(duration), &body_indent);
+    }
+    push_body(document, &task.description(), &body_indent);
+    if duration != 0 && format == OutlineFormat::Markdown {
+        push_body(document, &format.tracked_time(duration), &body_indent);
+    }
+    for record in read_records(task.id(), false, None, None)?.iter().rev() {
+        if !record.note().is_empty() {
+            push_body(document, &format.record_note(record), &body_indent);
+        }
+    }
+    for subtask in read_tasks(task.project(), None, None, Some(task.id()))? {
+        push_task(document, &subtask, format, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn push_project(document: &mut String, project: &Project, format: OutlineFormat) -> Result<()> {
+    let title = format!("{} {}", project.icon(), project.name());
+    let duration = project.duration();
+    let mut heading = format.heading(1, title.trim());
+    if duration != 0 && format == OutlineFormat::Org {
+        heading.truncate(heading.len() - 1);
+        heading.push_str(&format.tracked_time(duration));
+        heading.push_str("\n\n");
+    }
+    document.push_str(&heading);
+    let description = project.description();
+    if !description.trim().is_empty() {
+        document.push_str(description.trim());
+        document.push_str("\n\n");
+    }
+    if duration != 0 && format == OutlineFormat::Markdown {
+        document.push_str(&format.tracked_time(duration));
+        document.push_str("\n\n");
+    }
+    for list in read_lists(project.id())? {
+        document.push_str(&format.heading(2, &list.name()));
+        let tasks = read_tasks(project.id(), Some(list.id()), None, Some(0))?;
+        for task in &tasks {
+            push_task(document, task, format, 0)?;
+        }
+        if !tasks.is_empty() {
+            document.push('\n');
+        }
+    }
+    Ok(())
+}
+
+pub fn export_project(project: &Project, format: OutlineFormat) -> Result<String> {
+    let mut document = String::new();
+    push_project(&mut document, project, format)?;
+    Ok(document)
+}
+
+pub fn export_projects(format: OutlineFormat) -> Result<String> {
+    let mut document = String::new();
+    for project in read_projects(true)? {
+        push_project(&mut document, &project, format)?;
+    }
+    Ok(document)
+}
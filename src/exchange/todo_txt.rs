@@ -0,0 +1,197 @@
+use gettextrs::gettext;
+use rusqlite::Result;
+use std::collections::HashMap;
+
+use crate::db::models::{Project, Task};
+use crate::db::operations::{
+    create_list, create_project, create_task, read_lists, read_projects, read_tasks, update_task,
+};
+
+// Priorities are stored as 0 (none), 1 (low), 2 (medium) and 3 (high).
+// todo.txt letters map as (A) high, (B) medium and (C) to (Z) low.
+fn priority_from_letter(letter: char) -> u8 {
+    match letter {
+        'A' => 3,
+        'B' => 2,
+        _ => 1,
+    }
+}
+
+fn priority_to_letter(priority: u8) -> Option<char> {
+    match priority {
+        3 => Some('A'),
+        2 => Some('B'),
+        1 => Some('C'),
+        _ => None,
+    }
+}
+
+fn is_date(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| i == 4 || i == 7 || byte.is_ascii_digit())
+}
+
+// Project and list names can contain spaces but todo.txt tags can't
+fn tag_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TodoTxtLine {
+    pub name: String,
+    pub done: bool,
+    pub priority: u8,
+    pub project: Option<String>,
+    pub context: Option<String>,
+}
+
+pub fn parse_line(line: &str) -> Option<TodoTxtLine> {
+    let mut words = line.split_whitespace().peekable();
+    let mut todo = TodoTxtLine::default();
+
+    if words.peek() == Some(&"x") {
+        todo.done = true;
+        words.next();
+    }
+    if let Some(word) = words.peek() {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() == 3 && chars[0] == '(' && chars[1].is_ascii_uppercase() && chars[2] == ')' {
+            todo.priority = priority_from_letter(chars[1]);
+            words.next();
+        }
+    }
+    // Completion and creation dates
+    while words.peek().is_some_and(|word| is_date(word)) {
+        words.next();
+    }
+
+    let mut name_words = vec![];
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            if todo.project.is_none() {
+                todo.project = Some(project.to_string());
+            }
+        } else if let Some(context) = word.strip_prefix('@').filter(|tag| !tag.is_empty()) {
+            if todo.context.is_none() {
+                todo.context = Some(context.to_string());
+            }
+        } else {
+            name_words.push(word);
+        }
+    }
+    todo.name = name_words.join(" ");
+
+    if todo.name.is_empty() {
+        None
+    } else {
+        Some(todo)
+    }
+}
+
+pub fn format_task(task: &Task, project_name: &str, list_name: &str) -> String {
+    let mut line = String::new();
+    if task.done() {
+        line.push_str("x ");
+    }
+    if let Some(letter) = priority_to_letter(task.priority()) {
+        line.push_str(&format!("({letter}) "));
+    }
+    line.push_str(&task.name());
+    line.push_str(&format!(" +{}", tag_name(project_name)));
+    if !list_name.is_empty() {
+        line.push_str(&format!(" @{}", tag_name(list_name)));
+    }
+    line
+}
+
+// Subtasks follow their parent and use the list of their top level task
+fn push_task_lines(
+    lines: &mut Vec<String>,
+    task: &Task,
+    project_name: &str,
+    list_name: &str,
+) -> Result<()> {
+    lines.push(format_task(task, project_name, list_name));
+    for subtask in read_tasks(task.project(), None, None, Some(task.id()))? {
+        push_task_lines(lines, &subtask, project_name, list_name)?;
+    }
+    Ok(())
+}
+
+pub fn export_project(project: &Project) -> Result<String> {
+    let mut lines = vec![];
+    for list in read_lists(project.id())? {
+        for task in read_tasks(project.id(), Some(list.id()), None, Some(0))? {
+            push_task_lines(&mut lines, &task, &project.name(), &list.name())?;
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    Ok(text)
+}
+
+// Import tasks into the project unless they are tagged with another project.
+// Projects and lists are matched by name and created if they don't exist.
+pub fn import(text: &str, project_id: i64) -> Result<usize> {
+    let mut projects: HashMap<String, i64> = HashMap::new();
+    for project in read_projects(true)? {
+        projects
+            .entry(tag_name(&project.name()))
+            .or_insert(project.id());
+    }
+    let mut lists: HashMap<(i64, String), i64> = HashMap::new();
+
+    let mut count = 0;
+    // Tasks are shown by descending position so the first line should be created last
+    for todo in text.lines().rev().filter_map(parse_line) {
+        let project_id = if let Some(project) = &todo.project {
+            match projects.get(project) {
+                Some(id) => *id,
+                None => {
+                    let new_project = create_project(&project.replace('_', " "))?;
+                    projects.insert(project.to_string(), new_project.id());
+                    new_project.id()
+                }
+            }
+        } else {
+            project_id
+        };
+
+        let list_key = (project_id, todo.context.clone().unwrap_or_default());
+        let list_id = if let Some(list_id) = lists.get(&list_key) {
+            *list_id
+        } else {
+            let project_lists = read_lists(project_id)?;
+            let list = match &todo.context {
+                Some(context) => project_lists
+                    .into_iter()
+                    .find(|list| tag_name(&list.name()) == *context),
+                None => project_lists.into_iter().next(),
+            };
+            let list_id = match list {
+                Some(list) => list.id(),
+                None => {
+                    let name = todo.context.clone().unwrap_or(gettext("Tasks"));
+                    create_list(&name.replace('_', " "), project_id)?.id()
+                }
+            };
+            lists.insert(list_key, list_id);
+            list_id
+        };
+
+        let task = create_task(&todo.name, project_id, list_id, 0)?;
+        if todo.done || todo.priority != 0 {
+            task.set_done(todo.done);
+            task.set_priority(todo.priority);
+            update_task(&task)?;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
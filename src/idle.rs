@@ -0,0 +1,74 @@
+use gtk::{gio, glib, prelude::*};
+use std::cell::Cell;
+
+const MUTTER_NAME: &str = "org.gnome.Mutter.IdleMonitor";
+const MUTTER_PATH: &str = "/org/gnome/Mutter/IdleMonitor/Core";
+
+thread_local! {
+    static LAST_ACTIVITY: Cell<i64> = Cell::new(now());
+}
+
+fn now() -> i64 {
+    glib::DateTime::now_local().unwrap().to_unix()
+}
+
+// Input in IPlan windows, used by the fallback
+pub fn report_activity() {
+    LAST_ACTIVITY.with(|last_activity| last_activity.set(now()));
+}
+
+// Mutter knows about input in every application. The fallback only sees IPlan windows,
+// so working in other applications would look like being away. It is only used when
+// IPLAN_IDLE_MONITOR=fallback is set, which is also easy to drive by hand.
+pub enum IdleMonitor {
+    Mutter(gio::DBusProxy),
+    Fallback,
+    Unavailable,
+}
+
+impl IdleMonitor {
+    pub fn new() -> Self {
+        if std::env::var("IPLAN_IDLE_MONITOR").as_deref() == Ok("fallback") {
+            return IdleMonitor::Fallback;
+        }
+        let proxy = gio::DBusProxy::for_bus_sync(
+            gio::BusType::Session,
+            gio::DBusProxyFlags::DO_NOT_LOAD_PROPERTIES
+                | gio::DBusProxyFlags::DO_NOT_CONNECT_SIGNALS,
+            None,
+            MUTTER_NAME,
+            MUTTER_PATH,
+            MUTTER_NAME,
+            gio::Cancellable::NONE,
+        );
+        match proxy {
+            Ok(proxy) if proxy.name_owner().is_some() => IdleMonitor::Mutter(proxy),
+            _ => IdleMonitor::Unavailable,
+        }
+    }
+
+    // Seconds since the last user input, the callback is never called when unavailable
+    pub fn idle_time<F: FnOnce(i64) + 'static>(&self, callback: F) {
+        match self {
+            IdleMonitor::Mutter(proxy) => proxy.call(
+                "GetIdletime",
+                None,
+                gio::DBusCallFlags::NONE,
+                1000,
+                gio::Cancellable::NONE,
+                move |result| {
+                    let idle_time = result
+                        .ok()
+                        .and_then(|value| value.get::<(u64,)>())
+                        .map(|(milliseconds,)| (milliseconds / 1000) as i64)
+                        .unwrap_or(0);
+                    callback(idle_time);
+                },
+            ),
+            IdleMonitor::Fallback => {
+                callback(now() - LAST_ACTIVITY.with(|last_activity| last_activity.get()))
+            }
+            IdleMonitor::Unavailable => (),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use gtk::prelude::*;
+
+use crate::db::operations::{read_project, read_task};
+use crate::views::IPlanWindow;
+
+const SCHEME: &str = "iplan";
+
+// Links look like iplan://project/12 and iplan://task/345
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Link {
+    Project(i64),
+    Task(i64),
+}
+
+impl Link {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let path = uri.strip_prefix(SCHEME)?.strip_prefix("://")?;
+        let (kind, id) = path.trim_end_matches('/').split_once('/')?;
+        let id = id.parse().ok()?;
+        match kind {
+            "project" => Some(Link::Project(id)),
+            "task" => Some(Link::Task(id)),
+            _ => None,
+        }
+    }
+
+    pub fn uri(&self) -> String {
+        match self {
+            Link::Project(id) => format!("{SCHEME}://project/{id}"),
+            Link::Task(id) => format!("{SCHEME}://task/{id}"),
+        }
+    }
+
+    // Same as SearchWindow::handle_search_results_row_activated
+    pub fn open(&self, window: &IPlanWindow) {
+        let window_project_id = window.project().id();
+        match *self {
+            Link::Project(id) => {
+                let project = match read_project(id) {
+                    Ok(project) => project,
+                    Err(_) => return,
+                };
+                if window_project_id != project.id() {
+                    window.set_property("project", project);
+                    window
+                        .activate_action("search.project", None)
+                        .expect("Failed to send search.project action");
+                }
+            }
+            Link::Task(id) => {
+                let task = match read_task(id) {
+                    Ok(task) => task,
+                    Err(_) => return,
+                };
+                let project = read_project(task.project()).expect("Failed to read project");
+                let project_changed = if window_project_id != project.id() {
+                    window.set_property("project", project);
+                    true
+                } else {
+                    false
+                };
+                let (action, parameter) = if task.done() {
+                    (
+                        "search.task-done",
+                        (project_changed, task.id(), task.list()).to_variant(),
+                    )
+                } else {
+                    ("search.task", (project_changed, task.id()).to_variant())
+                };
+                window
+                    .activate_action(action, Some(&parameter))
+                    .expect("Failed to send search action");
+            }
+        }
+    }
+}
@@ -19,8 +19,17 @@
  */
 
 mod application;
+mod caldav;
+mod cli;
 mod config;
 mod db;
+mod dbus;
+mod exchange;
+mod idle;
+mod link;
+mod quick_add;
+mod search_provider;
+mod timer;
 mod views;
 
 use self::application::IPlanApplication;
@@ -44,11 +53,17 @@ fn main() {
     // Check database
     db::check_database().expect("Database check failed");
 
+    // Subcommands run without the interface
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     // Create a new GtkApplication. The application manages our main loop,
     // application windows, integration with the window manager/compositor, and
     // desktop features such as file opening and single-instance applications.
 
-    let app = IPlanApplication::new(APPLICATION_ID, &gio::ApplicationFlags::empty());
+    let app = IPlanApplication::new(APPLICATION_ID, &gio::ApplicationFlags::HANDLES_OPEN);
 
     // Run the application. This function will block until the application
     // exits. Upon return, we have our exit code to return to the shell. (This
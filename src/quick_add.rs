@@ -0,0 +1,276 @@
+use gettextrs::gettext;
+use gtk::glib;
+
+use crate::db::models::{List, Task};
+
+// Metadata typed into the name of a new task, for example
+// "Call the bank tomorrow 5pm #finance !high ~30m >Backlog"
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QuickAdd {
+    pub name: String,
+    // Start of the day in local time, same as Task::due_date
+    pub due_date: Option<i64>,
+    pub tags: Vec<String>,
+    pub priority: Option<u8>,
+    // Seconds
+    pub estimate: Option<i64>,
+    pub list: Option<String>,
+    // Recognized words that have no effect, like the time after a due date
+    pub ignored: Vec<String>,
+}
+
+impl QuickAdd {
+    // Words that aren't recognized are kept in the name in the same order. Due dates are
+    // only taken from the end of the name, so "Prepare monday standup" keeps its words.
+    // Tags are not only digits, #123 is usually an issue number.
+    pub fn parse(text: &str) -> Self {
+        let mut quick_add = Self::default();
+        let mut name = Vec::new();
+        for word in text.split_whitespace() {
+            if let Some(tag) = word
+                .strip_prefix('#')
+                .filter(|tag| !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()))
+            {
+                if !quick_add.tags.iter().any(|other| other == tag) {
+                    quick_add.tags.push(tag.to_string());
+                }
+            } else if let Some(priority) = word.strip_prefix('!').and_then(parse_priority) {
+                quick_add.priority = Some(priority);
+            } else if let Some(estimate) = word.strip_prefix('~').and_then(parse_estimate) {
+                quick_add.estimate = Some(estimate);
+            } else if let Some(list) = word.strip_prefix('>').filter(|list| !list.is_empty()) {
+                quick_add.list = Some(list.to_string());
+            } else {
+                name.push(word);
+            }
+        }
+        // The longest phrase wins, like "next friday" over "friday". At least one word
+        // stays so "Today" alone is still a name.
+        for count in (1..name.len().min(4)).rev() {
+            let start = name.len() - count;
+            if let Some((due_date, used)) = parse_due_date(&name[start..]) {
+                if used == count {
+                    quick_add.due_date = Some(due_date);
+                    if is_time(name[name.len() - 1]) {
+                        quick_add.ignored.push(name[name.len() - 1].to_string());
+                    }
+                    name.truncate(start);
+                    break;
+                }
+            }
+        }
+        quick_add.name = name.join(" ");
+        quick_add
+    }
+
+    // List names with spaces are typed with underscores, like >Next_Week
+    pub fn find_list<'a>(&self, lists: &'a [List]) -> Option<&'a List> {
+        let target = self.list.as_ref()?.to_lowercase().replace('_', " ");
+        lists
+            .iter()
+            .find(|list| list.name().to_lowercase() == target)
+    }
+
+    // Short description of what is recognized, empty when there is nothing
+    pub fn preview(&self, lists: &[List]) -> String {
+        let mut parts = Vec::new();
+        if let Some(due_date) = self.due_date {
+            let date = glib::DateTime::from_unix_local(due_date).unwrap();
+            let date = date.format("%a %e %b").unwrap();
+            parts.push(gettext("Due {}").replace("{}", date.trim()));
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|tag| format!("#{tag}")).collect();
+            parts.push(tags.join(" "));
+        }
+        match self.priority {
+            Some(3) => parts.push(gettext("High priority")),
+            Some(2) => parts.push(gettext("Medium priority")),
+            Some(1) => parts.push(gettext("Low priority")),
+            _ => (),
+        }
+        if let Some(estimate) = self.estimate {
+            parts.push(gettext("Estimate {}").replace("{}", &estimate_display(estimate)));
+        }
+        if let Some(list) = &self.list {
+            match self.find_list(lists) {
+                Some(list) => parts.push(gettext("In {}").replace("{}", &list.name())),
+                None => parts.push(gettext("No list named {}").replace("{}", list)),
+            }
+        }
+        if !self.ignored.is_empty() {
+            // Translators: {} Will be replaced with words like "5pm", due dates have no time.
+            parts.push(gettext("Ignored {}").replace("{}", &self.ignored.join(" ")));
+        }
+        parts.join(" · ")
+    }
+
+    // The target list is left to the caller because the task row has to move too
+    pub fn apply(&self, task: &Task) {
+        task.set_name(self.name.as_str());
+        if let Some(due_date) = self.due_date {
+            task.set_due_date(due_date);
+        }
+        if !self.tags.is_empty() {
+            task.set_tags(self.tags.join(" "));
+        }
+        if let Some(priority) = self.priority {
+            task.set_priority(priority);
+        }
+        if let Some(estimate) = self.estimate {
+            task.set_estimate(estimate);
+        }
+    }
+}
+
+pub fn estimate_display(estimate: i64) -> String {
+    let (hours, minutes) = (estimate / 3600, estimate / 60 % 60);
+    if hours == 0 {
+        gettext("{} min").replace("{}", &minutes.to_string())
+    } else if minutes == 0 {
+        gettext("{} h").replace("{}", &hours.to_string())
+    } else {
+        gettext("{} h {} min")
+            .replacen("{}", &hours.to_string(), 1)
+            .replacen("{}", &minutes.to_string(), 1)
+    }
+}
+
+// Keywords are accepted in English and in the language of the user
+fn is_keyword(word: &str, english: &str, translated: &str) -> bool {
+    word == english || word == translated.to_lowercase()
+}
+
+// Same values as todo_txt: 1 (low), 2 (medium) and 3 (high)
+fn parse_priority(word: &str) -> Option<u8> {
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "3" | "h" | "high" => Some(3),
+        "2" | "m" | "med" | "medium" => Some(2),
+        "1" | "l" | "low" => Some(1),
+        _ if word == gettext("high").to_lowercase() => Some(3),
+        _ if word == gettext("medium").to_lowercase() => Some(2),
+        _ if word == gettext("low").to_lowercase() => Some(1),
+        _ => None,
+    }
+}
+
+// 30m, 1h, 1h30m, 1.5h or only minutes like 90
+fn parse_estimate(text: &str) -> Option<i64> {
+    if let Ok(minutes) = text.parse::<u32>() {
+        return (minutes > 0).then_some(minutes as i64 * 60);
+    }
+    let mut total = 0.0;
+    let mut rest = text.to_lowercase();
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let number: f64 = rest[..number_end].parse().ok()?;
+        let rest_unit = &rest[number_end..];
+        let unit_end = rest_unit
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest_unit.len());
+        let seconds = match &rest_unit[..unit_end] {
+            "h" | "hr" | "hrs" => 3600.0,
+            "m" | "min" | "mins" => 60.0,
+            _ => return None,
+        };
+        total += number * seconds;
+        rest = rest_unit[unit_end..].to_string();
+    }
+    (total >= 60.0).then_some(total as i64)
+}
+
+fn start_of_today() -> glib::DateTime {
+    let now = glib::DateTime::now_local().unwrap();
+    glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap()
+}
+
+// Day of week from 1 (Monday) to 7 (Sunday), localized names come from glib.
+// Short names like "fri" are only accepted after "next" to keep words like "sun" in names.
+fn parse_weekday(word: &str, short: bool) -> Option<i32> {
+    const WEEKDAYS: [&str; 7] = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+    if let Some(i) = WEEKDAYS.iter().position(|weekday| {
+        word == *weekday || (short && word.len() >= 3 && weekday.starts_with(word))
+    }) {
+        return Some(i as i32 + 1);
+    }
+    let today = start_of_today();
+    (0..7).find_map(|days| {
+        let date = today.add_days(days).unwrap();
+        let full_name = date.format("%A").unwrap().to_lowercase();
+        let short_name = date.format("%a").unwrap().to_lowercase();
+        (word == full_name || (short && word == short_name)).then(|| date.day_of_week())
+    })
+}
+
+// 5pm, 5:30pm or 17:00
+fn is_time(word: &str) -> bool {
+    let word = word.to_lowercase();
+    let clock = word
+        .strip_suffix("am")
+        .or_else(|| word.strip_suffix("pm"))
+        .unwrap_or(&word);
+    let has_suffix = clock.len() != word.len();
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute),
+        None if has_suffix => (clock, "00"),
+        _ => return false,
+    };
+    match (hour.parse::<u8>(), minute.parse::<u8>()) {
+        (Ok(hour), Ok(minute)) if minute < 60 => {
+            if has_suffix {
+                (1..=12).contains(&hour)
+            } else {
+                hour < 24
+            }
+        }
+        _ => false,
+    }
+}
+
+// Returns the due date and the number of words used. Due dates are whole days,
+// so a time after the date is recognized but not kept.
+fn parse_due_date(words: &[&str]) -> Option<(i64, usize)> {
+    let first = words.first()?.to_lowercase();
+    let today = start_of_today();
+    let (date, mut count) = if is_keyword(&first, "today", &gettext("today")) {
+        (today, 1)
+    } else if is_keyword(&first, "tomorrow", &gettext("tomorrow")) {
+        (today.add_days(1).unwrap(), 1)
+    } else if is_keyword(&first, "next", &gettext("next")) {
+        let second = words.get(1)?.to_lowercase();
+        if is_keyword(&second, "week", &gettext("week")) {
+            (today.add_days(7).unwrap(), 2)
+        } else {
+            // The coming one, never today
+            let weekday = parse_weekday(&second, true)?;
+            let days = (weekday - today.day_of_week() + 6).rem_euclid(7) + 1;
+            (today.add_days(days).unwrap(), 2)
+        }
+    } else if let Some(weekday) = parse_weekday(&first, false) {
+        let days = (weekday - today.day_of_week()).rem_euclid(7);
+        (today.add_days(days).unwrap(), 1)
+    } else {
+        // 2024-05-30
+        let mut parts = first.splitn(3, '-').map(|part| part.parse::<i32>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) if year > 999 => (
+                glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()?,
+                1,
+            ),
+            _ => return None,
+        }
+    };
+    if words.get(count).is_some_and(|word| is_time(word)) {
+        count += 1;
+    }
+    Some((date.to_unix(), count))
+}
@@ -0,0 +1,161 @@
+use gettextrs::gettext;
+use gtk::{gio, glib, prelude::*};
+use std::collections::HashMap;
+
+use crate::application::IPlanApplication;
+use crate::db::models::SearchFilter;
+use crate::db::operations::{find_projects, find_tasks, read_project, read_task};
+use crate::link::Link;
+use crate::views::IPlanWindow;
+
+const OBJECT_PATH_SUFFIX: &str = "/SearchProvider";
+
+const INTERFACE_NAME: &str = "org.gnome.Shell.SearchProvider2";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="as" name="results" direction="out" />
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in" />
+      <arg type="aa{sv}" name="metas" direction="out" />
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in" />
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in" />
+      <arg type="u" name="timestamp" direction="in" />
+    </method>
+  </interface>
+</node>
+"#;
+
+pub fn register(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info.lookup_interface(INTERFACE_NAME).unwrap();
+    connection.register_object(
+        &format!("{object_path}{OBJECT_PATH_SUFFIX}"),
+        &interface_info,
+        |_connection, _sender, _path, _interface, method, parameters, invocation| {
+            match handle_method(method, &parameters) {
+                Some(value) => invocation.return_value(value.as_ref()),
+                None => invocation.return_dbus_error(
+                    "org.freedesktop.DBus.Error.InvalidArgs",
+                    "Invalid arguments",
+                ),
+            }
+        },
+        |_connection, _sender, _path, _interface, _property| unreachable!(),
+        |_connection, _sender, _path, _interface, _property, _value| false,
+    )
+}
+
+// Returns None for invalid arguments
+fn handle_method(method: &str, parameters: &glib::Variant) -> Option<Option<glib::Variant>> {
+    match method {
+        "GetInitialResultSet" => {
+            let (terms,) = parameters.get::<(Vec<String>,)>()?;
+            Some(Some((search(&terms),).to_variant()))
+        }
+        "GetSubsearchResultSet" => {
+            let (_previous_results, terms) = parameters.get::<(Vec<String>, Vec<String>)>()?;
+            Some(Some((search(&terms),).to_variant()))
+        }
+        "GetResultMetas" => {
+            let (identifiers,) = parameters.get::<(Vec<String>,)>()?;
+            let metas: Vec<HashMap<String, glib::Variant>> = identifiers
+                .iter()
+                .filter_map(|identifier| result_meta(identifier))
+                .collect();
+            Some(Some((metas,).to_variant()))
+        }
+        "ActivateResult" => {
+            let (identifier, _terms, _timestamp) =
+                parameters.get::<(String, Vec<String>, u32)>()?;
+            activate_result(Link::parse(&identifier)?);
+            Some(None)
+        }
+        "LaunchSearch" => {
+            let (terms, _timestamp) = parameters.get::<(Vec<String>, u32)>()?;
+            if let Some(app) = gio::Application::default().and_downcast::<IPlanApplication>() {
+                app.activate();
+                app.show_search(&terms.join(" "));
+            }
+            Some(None)
+        }
+        _ => None,
+    }
+}
+
+// Done tasks and archived projects are left out like the in-app search default.
+// Database errors give no results, GNOME Shell waits for the reply.
+fn search(terms: &[String]) -> Vec<String> {
+    let text = terms.join(" ").to_lowercase();
+    let text = text.trim();
+    if text.is_empty() {
+        return vec![];
+    }
+    let (projects, tasks) = match (
+        find_projects(text, false),
+        find_tasks(text, false, SearchFilter::All),
+    ) {
+        (Ok(projects), Ok(tasks)) => (projects, tasks),
+        _ => return vec![],
+    };
+    let mut results = vec![];
+    for project in projects {
+        results.push(Link::Project(project.id()).uri());
+    }
+    for task in tasks {
+        results.push(Link::Task(task.id()).uri());
+    }
+    results
+}
+
+fn result_meta(identifier: &str) -> Option<HashMap<String, glib::Variant>> {
+    let (name, description) = match Link::parse(identifier)? {
+        Link::Project(id) => {
+            let project = read_project(id).ok()?;
+            (project.name(), gettext("Project"))
+        }
+        Link::Task(id) => {
+            let task = read_task(id).ok()?;
+            let project = read_project(task.project()).ok()?;
+            // Translators: {} Will be replaced with the project name.
+            let description = gettext("Task in {}").replace("{}", &project.name());
+            (task.name(), description)
+        }
+    };
+    let mut meta = HashMap::new();
+    meta.insert("id".to_string(), identifier.to_variant());
+    meta.insert("name".to_string(), name.to_variant());
+    meta.insert("description".to_string(), description.to_variant());
+    meta.insert("gicon".to_string(), "ir.imansalmani.IPlan".to_variant());
+    Some(meta)
+}
+
+fn activate_result(link: Link) {
+    let app = match gio::Application::default().and_downcast::<gtk::Application>() {
+        Some(app) => app,
+        None => return,
+    };
+    app.activate();
+    if let Some(window) = app.active_window().and_downcast::<IPlanWindow>() {
+        link.open(&window);
+        window.present();
+    }
+}
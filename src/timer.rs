@@ -0,0 +1,455 @@
+use gettextrs::gettext;
+use gtk::glib::{self, once_cell::sync::Lazy, subclass::prelude::*, subclass::Signal};
+use gtk::{gio, prelude::*};
+use rusqlite::Result;
+use std::cell::{Cell, RefCell};
+
+use crate::db::models::Record;
+use crate::db::operations::{
+    create_record, delete_record, read_incomplete_records, read_task, update_record,
+};
+use crate::idle::IdleMonitor;
+
+fn now() -> i64 {
+    glib::DateTime::now_local().unwrap().to_unix()
+}
+
+// A running timer is a record without duration. Of the incomplete records, newest first,
+// only the newest keeps running and older ones end where the next one starts.
+pub fn repair_incomplete_records(records: Vec<Record>) -> Result<Option<Record>> {
+    let mut records = records.into_iter();
+    let newest = match records.next() {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+    let mut next_start = newest.start();
+    for record in records {
+        record.set_duration((next_start - record.start()).max(1));
+        update_record(&record)?;
+        next_start = record.start();
+    }
+    Ok(Some(newest))
+}
+
+pub fn stop_record(record: &Record) -> Result<()> {
+    record.set_duration(now() - record.start());
+    update_record(record)
+}
+
+// Only one timer runs at a time, so the others are stopped
+pub fn start(task_id: i64) -> Result<Record> {
+    let mut running = None;
+    for record in read_incomplete_records()? {
+        if record.task() == task_id && running.is_none() {
+            running = Some(record);
+        } else {
+            stop_record(&record)?;
+        }
+    }
+    match running {
+        Some(record) => Ok(record),
+        None => create_record(now(), task_id, 0, ""),
+    }
+}
+
+pub fn stop() -> Result<()> {
+    for record in read_incomplete_records()? {
+        stop_record(&record)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TimerPhase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct TimerService {
+        pub record: RefCell<Option<Record>>,
+        pub tick_source: RefCell<Option<glib::SourceId>>,
+        pub settings: RefCell<Option<gio::Settings>>,
+        // Pomodoro cycle, the task is zero when no cycle is running
+        pub pomodoro_task: Cell<i64>,
+        pub phase: Cell<TimerPhase>,
+        pub phase_start: Cell<i64>,
+        pub sessions: Cell<i32>,
+        pub last_tick: Cell<i64>,
+        // Ticks can be late or coalesced, so idle checks run by the time since the last one
+        pub last_idle_check: Cell<i64>,
+        pub idle_monitor: RefCell<Option<IdleMonitor>>,
+        // Start of the current idle time, zero while the user is active
+        pub idle_start: Cell<i64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimerService {
+        const NAME: &'static str = "TimerService";
+        type Type = super::TimerService;
+    }
+
+    impl ObjectImpl for TimerService {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![
+                    Signal::builder("changed").build(),
+                    Signal::builder("tick")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    Signal::builder("resumed")
+                        .param_types([i64::static_type()])
+                        .build(),
+                    Signal::builder("idle-returned")
+                        .param_types([i64::static_type()])
+                        .build(),
+                ]
+            });
+            SIGNALS.as_ref()
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct TimerService(ObjectSubclass<imp::TimerService>);
+}
+
+thread_local! {
+    static TIMER_SERVICE: TimerService = {
+        let service = glib::Object::new::<TimerService>();
+        service.reload().expect("Failed to read active record");
+        service
+    };
+}
+
+// Owner of the active record, every change to the running timer goes through it
+impl Default for TimerService {
+    fn default() -> Self {
+        TIMER_SERVICE.with(|service| service.clone())
+    }
+}
+
+// Listeners connect to "changed" for starts, stops and Pomodoro transitions,
+// "tick" for the elapsed seconds, "resumed" with the last tick before a suspend
+// and "idle-returned" with the start of the idle time when the user comes back
+impl TimerService {
+    pub fn record(&self) -> Option<Record> {
+        self.imp().record.borrow().clone()
+    }
+
+    pub fn task_id(&self) -> Option<i64> {
+        self.imp().record.borrow().as_ref().map(Record::task)
+    }
+
+    pub fn phase(&self) -> TimerPhase {
+        self.imp().phase.get()
+    }
+
+    // Task of the running Pomodoro cycle, including its breaks
+    fn pomodoro_task_id(&self) -> Option<i64> {
+        match self.imp().pomodoro_task.get() {
+            0 => None,
+            task_id => Some(task_id),
+        }
+    }
+
+    // Task of the running timer or the Pomodoro break
+    pub fn active_task_id(&self) -> Option<i64> {
+        self.pomodoro_task_id().or_else(|| self.task_id())
+    }
+
+    pub fn elapsed(&self) -> i64 {
+        let imp = self.imp();
+        match imp.record.borrow().as_ref() {
+            Some(record) => now() - record.start(),
+            None if self.pomodoro_task_id().is_some() => now() - imp.phase_start.get(),
+            None => 0,
+        }
+    }
+
+    // Seconds left in the current Pomodoro session or break
+    pub fn remaining(&self) -> Option<i64> {
+        self.pomodoro_task_id()?;
+        Some(self.phase_length() - self.elapsed())
+    }
+
+    // Reads the active record again after changes from outside, the command line
+    // asks for it over D-Bus
+    pub fn reload(&self) -> Result<()> {
+        let imp = self.imp();
+        let record = read_incomplete_records().and_then(repair_incomplete_records)?;
+        // Only a break of an unfinished task keeps the Pomodoro cycle without a record
+        if let Some(task_id) = self.pomodoro_task_id() {
+            let ended = match &record {
+                Some(record) => record.task() != task_id,
+                None => {
+                    self.phase() == TimerPhase::Work
+                        || !read_task(task_id).is_ok_and(|task| !task.done())
+                }
+            };
+            if ended {
+                imp.pomodoro_task.set(0);
+                imp.phase.set(TimerPhase::Work);
+            }
+        }
+        self.set_record(record);
+        Ok(())
+    }
+
+    pub fn start(&self, task_id: i64) -> Result<Record> {
+        let imp = self.imp();
+        let record = start(task_id)?;
+        if self.settings().boolean("pomodoro") {
+            if imp.pomodoro_task.get() != task_id {
+                imp.sessions.set(0);
+            }
+            imp.pomodoro_task.set(task_id);
+            imp.phase.set(TimerPhase::Work);
+            imp.phase_start.set(record.start());
+        } else {
+            imp.pomodoro_task.set(0);
+        }
+        self.set_record(Some(record.clone()));
+        Ok(record)
+    }
+
+    // A timer running longer than the preferred hours is probably forgotten
+    pub fn stale_record(&self) -> Option<Record> {
+        let record = self.record()?;
+        let threshold = self.settings().int("stale-timer-hours") as i64 * 3600;
+        if now() - record.start() > threshold {
+            Some(record)
+        } else {
+            None
+        }
+    }
+
+    pub fn trim(&self, end: i64) -> Result<()> {
+        if let Some(record) = self.record() {
+            record.set_duration((end - record.start()).max(1));
+            update_record(&record)?;
+        }
+        self.imp().pomodoro_task.set(0);
+        self.set_record(None);
+        Ok(())
+    }
+
+    pub fn discard(&self) -> Result<()> {
+        if let Some(record) = self.record() {
+            delete_record(record.id())?;
+        }
+        self.imp().pomodoro_task.set(0);
+        self.set_record(None);
+        Ok(())
+    }
+
+    // Deleting a task also deletes its records and subtasks, the timer can't keep one of them
+    pub fn release_task(&self, task_id: i64) -> Result<()> {
+        let owned = match self.active_task_id() {
+            Some(active_id) if active_id == task_id => true,
+            Some(active_id) => read_task(active_id).is_ok_and(|task| task.parent() == task_id),
+            None => false,
+        };
+        if owned {
+            self.discard()?;
+        }
+        Ok(())
+    }
+
+    // After a list or project is deleted the timer may point to a task that no longer exists
+    pub fn forget_deleted(&self) {
+        if let Some(task_id) = self.active_task_id() {
+            if read_task(task_id).is_err() {
+                self.imp().pomodoro_task.set(0);
+                self.set_record(None);
+            }
+        }
+    }
+
+    // Ends the record where the user went idle and continues with a new one
+    pub fn remove_idle_time(&self, idle_start: i64, keep_as_record: bool) -> Result<()> {
+        let record = match self.record() {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+        let idle_start = idle_start.max(record.start());
+        record.set_duration((idle_start - record.start()).max(1));
+        update_record(&record)?;
+        if keep_as_record {
+            create_record(idle_start, record.task(), (now() - idle_start).max(1), "")?;
+        }
+        let new_record = create_record(now(), record.task(), 0, "")?;
+        self.set_record(Some(new_record));
+        Ok(())
+    }
+
+    // The note describes the work done in the session
+    pub fn stop_with_note(&self, note: &str) -> Result<()> {
+        if let Some(record) = self.record() {
+            record.set_note(note.trim());
+            update_record(&record)?;
+        }
+        self.stop()
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        let imp = self.imp();
+        self.save_break()?;
+        stop()?;
+        imp.pomodoro_task.set(0);
+        imp.phase.set(TimerPhase::Work);
+        self.set_record(None);
+        Ok(())
+    }
+
+    fn settings(&self) -> gio::Settings {
+        self.imp()
+            .settings
+            .borrow_mut()
+            .get_or_insert_with(|| gio::Settings::new("ir.imansalmani.IPlan.Preferences"))
+            .clone()
+    }
+
+    fn phase_length(&self) -> i64 {
+        let key = match self.phase() {
+            TimerPhase::Work => "pomodoro-work",
+            TimerPhase::ShortBreak => "pomodoro-short-break",
+            TimerPhase::LongBreak => "pomodoro-long-break",
+        };
+        self.settings().int(key) as i64 * 60
+    }
+
+    fn set_record(&self, record: Option<Record>) {
+        let imp = self.imp();
+        if let Some(source) = imp.tick_source.take() {
+            source.remove();
+        }
+        if record.is_some() || imp.pomodoro_task.get() != 0 {
+            let source = glib::timeout_add_seconds_local(
+                1,
+                glib::clone!(@weak self as obj => @default-return glib::Continue(false), move || {
+                    obj.tick();
+                    glib::Continue(true)
+                }),
+            );
+            imp.tick_source.replace(Some(source));
+        }
+        imp.last_tick.set(0);
+        imp.idle_start.set(0);
+        imp.record.replace(record);
+        self.emit_by_name::<()>("changed", &[]);
+    }
+
+    fn tick(&self) {
+        let imp = self.imp();
+        // Timeouts don't run while the system is suspended
+        let last_tick = imp.last_tick.replace(now());
+        if last_tick != 0 && now() - last_tick > 60 {
+            self.emit_by_name::<()>("resumed", &[&last_tick]);
+        }
+        if self.pomodoro_task_id().is_some() && self.remaining().unwrap() <= 0 {
+            let result = match self.phase() {
+                TimerPhase::Work => self.start_break(),
+                TimerPhase::ShortBreak | TimerPhase::LongBreak => self
+                    .save_break()
+                    .and_then(|_| self.start(imp.pomodoro_task.get()))
+                    .map(|_| ()),
+            };
+            result.expect("Failed to switch Pomodoro phase");
+            self.notify_phase();
+            return;
+        }
+        if self.record().is_some() && now() - imp.last_idle_check.get() >= 5 {
+            imp.last_idle_check.set(now());
+            self.check_idle();
+        }
+        self.emit_by_name::<()>("tick", &[&self.elapsed()]);
+    }
+
+    fn check_idle(&self) {
+        let settings = self.settings();
+        if !settings.boolean("idle-detection") {
+            return;
+        }
+        let threshold = settings.int("idle-minutes") as i64 * 60;
+        let imp = self.imp();
+        let mut idle_monitor = imp.idle_monitor.borrow_mut();
+        let idle_monitor = idle_monitor.get_or_insert_with(IdleMonitor::new);
+        idle_monitor.idle_time(glib::clone!(@weak self as obj => move |idle_time| {
+            let imp = obj.imp();
+            if idle_time >= threshold && imp.idle_start.get() == 0 {
+                imp.idle_start.set(now() - idle_time);
+            } else if idle_time < threshold && imp.idle_start.get() != 0 {
+                let idle_start = imp.idle_start.replace(0);
+                obj.emit_by_name::<()>("idle-returned", &[&idle_start]);
+            }
+        }));
+    }
+
+    // The work session becomes a record of its own and the break is not tracked as work
+    fn start_break(&self) -> Result<()> {
+        let imp = self.imp();
+        stop()?;
+        let sessions = imp.sessions.get() + 1;
+        imp.sessions.set(sessions);
+        let interval = self.settings().int("pomodoro-long-break-interval");
+        if sessions % interval == 0 {
+            imp.phase.set(TimerPhase::LongBreak);
+        } else {
+            imp.phase.set(TimerPhase::ShortBreak);
+        }
+        imp.phase_start.set(now());
+        self.set_record(None);
+        Ok(())
+    }
+
+    // Breaks become records of the cycle's task when they are tracked. They are not
+    // billable and can't be longer than the break, even after a suspend.
+    fn save_break(&self) -> Result<()> {
+        let imp = self.imp();
+        let note = match self.phase() {
+            TimerPhase::Work => return Ok(()),
+            TimerPhase::ShortBreak => gettext("Short Break"),
+            TimerPhase::LongBreak => gettext("Long Break"),
+        };
+        let task_id = imp.pomodoro_task.get();
+        if task_id == 0 || !self.settings().boolean("pomodoro-track-breaks") {
+            return Ok(());
+        }
+        let start = imp.phase_start.get();
+        let duration = (now() - start).clamp(1, self.phase_length());
+        let record = create_record(start, task_id, duration, &note)?;
+        record.set_billable(false);
+        update_record(&record)
+    }
+
+    fn notify_phase(&self) {
+        let app = match gio::Application::default() {
+            Some(app) => app,
+            None => return,
+        };
+        let minutes = self.phase_length() / 60;
+        let (title, body) = match self.phase() {
+            TimerPhase::Work => (
+                gettext("Break is over"),
+                gettext("Work session of {} minutes started").replace("{}", &minutes.to_string()),
+            ),
+            TimerPhase::ShortBreak => (
+                gettext("Time for a short break"),
+                gettext("Rest for {} minutes").replace("{}", &minutes.to_string()),
+            ),
+            TimerPhase::LongBreak => (
+                gettext("Time for a long break"),
+                gettext("Rest for {} minutes").replace("{}", &minutes.to_string()),
+            ),
+        };
+        let notification = gio::Notification::new(&title);
+        notification.set_body(Some(&body));
+        app.send_notification(Some("pomodoro"), &notification);
+    }
+}
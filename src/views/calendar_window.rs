@@ -0,0 +1,539 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{cairo, glib};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use super::statistics_window::COLORS;
+use crate::db::models::{Record, Task};
+use crate::db::operations::{
+    create_record, find_overlapping_records, read_projects, read_task, read_tasks, update_record,
+};
+use crate::views::IPlanWindow;
+use crate::IPlanApplication;
+
+const TIME_WIDTH: f64 = 48.0;
+const HOUR_HEIGHT: f64 = 48.0;
+// Distance from the block edges in pixels that starts a resize
+const EDGE_SIZE: f64 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Start,
+    End,
+}
+
+// A record and how it shows in the timeline
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub record: Record,
+    pub task: String,
+    pub project: String,
+    pub color: (f64, f64, f64),
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/calendar_window.ui")]
+    pub struct CalendarWindow {
+        // Start of the first visible day
+        pub start: Cell<i64>,
+        pub blocks: RefCell<Vec<Block>>,
+        // Block being resized and the time of its edge before the drag
+        pub drag: RefCell<Option<(Block, Edge, i64)>>,
+        pub scrolled: Cell<bool>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub view_drop_down: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub days_header: TemplateChild<gtk::DrawingArea>,
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub timeline: TemplateChild<gtk::DrawingArea>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CalendarWindow {
+        const NAME: &'static str = "CalendarWindow";
+        type Type = super::CalendarWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for CalendarWindow {}
+    impl WidgetImpl for CalendarWindow {}
+    impl WindowImpl for CalendarWindow {}
+    impl AdwWindowImpl for CalendarWindow {}
+}
+
+glib::wrapper! {
+    pub struct CalendarWindow(ObjectSubclass<imp::CalendarWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl CalendarWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        win.imp().start.set(today().to_unix());
+        win.set_draw_funcs();
+        win.add_controllers();
+        win.load();
+        win
+    }
+
+    fn days(&self) -> i32 {
+        if self.imp().view_drop_down.selected() == 0 {
+            1
+        } else {
+            7
+        }
+    }
+
+    fn day_start(&self, column: i32) -> i64 {
+        glib::DateTime::from_unix_local(self.imp().start.get())
+            .unwrap()
+            .add_days(column)
+            .unwrap()
+            .to_unix()
+    }
+
+    fn column_width(&self) -> f64 {
+        (self.imp().timeline.width() as f64 - TIME_WIDTH) / self.days() as f64
+    }
+
+    fn time_y(&self, time: i64, column: i32) -> f64 {
+        (time - self.day_start(column)) as f64 / 3600.0 * HOUR_HEIGHT
+    }
+
+    fn point_time(&self, x: f64, y: f64) -> Option<(i32, i64)> {
+        let column = ((x - TIME_WIDTH) / self.column_width()).floor();
+        if column < 0.0 || column >= self.days() as f64 {
+            return None;
+        }
+        let column = column as i32;
+        Some((
+            column,
+            self.day_start(column) + (y / HOUR_HEIGHT * 3600.0) as i64,
+        ))
+    }
+
+    // Parts of the blocks in each day, with their start and end in that day
+    fn segments(&self) -> Vec<(Block, i32, i64, i64)> {
+        let mut segments = vec![];
+        for block in self.imp().blocks.borrow().iter() {
+            for column in 0..self.days() {
+                let start = block.record.start().max(self.day_start(column));
+                let end = block.record.end().min(self.day_start(column + 1));
+                if start < end {
+                    segments.push((block.clone(), column, start, end));
+                }
+            }
+        }
+        segments
+    }
+
+    fn block_at(&self, x: f64, y: f64) -> Option<(Block, Option<Edge>)> {
+        let (column, _) = self.point_time(x, y)?;
+        for (block, segment_column, start, end) in self.segments() {
+            if segment_column != column {
+                continue;
+            }
+            let (top, bottom) = (self.time_y(start, column), self.time_y(end, column));
+            if start == block.record.start() && (y - top).abs() <= EDGE_SIZE {
+                return Some((block, Some(Edge::Start)));
+            }
+            if end == block.record.end() && (y - bottom).abs() <= EDGE_SIZE {
+                return Some((block, Some(Edge::End)));
+            }
+            if y > top && y < bottom {
+                return Some((block, None));
+            }
+        }
+        None
+    }
+
+    fn load(&self) {
+        let imp = self.imp();
+        let start = imp.start.get();
+        let end = self.day_start(self.days());
+        let projects = read_projects(true).expect("Failed to read projects");
+        let mut tasks: HashMap<i64, Task> = HashMap::new();
+        let mut blocks = vec![];
+        for record in find_overlapping_records(start, end, 0).expect("Failed to read records") {
+            let task = tasks
+                .entry(record.task())
+                .or_insert_with(|| read_task(record.task()).expect("Failed to read task"));
+            let project = projects
+                .iter()
+                .find(|project| project.id() == task.project());
+            let index = project.map(|project| project.index()).unwrap_or(0) as usize;
+            blocks.push(Block {
+                record,
+                task: task.name(),
+                project: project.map(|project| project.name()).unwrap_or_default(),
+                color: COLORS[index % COLORS.len()],
+            });
+        }
+        imp.blocks.replace(blocks);
+
+        let first_day = glib::DateTime::from_unix_local(start).unwrap();
+        let title = if self.days() == 1 {
+            first_day.format("%A %e %B %Y").unwrap().to_string()
+        } else {
+            let last_day = first_day.add_days(self.days() - 1).unwrap();
+            format!(
+                "{} – {}",
+                first_day.format("%e %B").unwrap(),
+                last_day.format("%e %B %Y").unwrap()
+            )
+        };
+        imp.title_label.set_label(title.trim());
+        imp.days_header.queue_draw();
+        imp.timeline.queue_draw();
+    }
+
+    fn set_draw_funcs(&self) {
+        let imp = self.imp();
+        imp.days_header.set_draw_func(
+            glib::clone!(@weak self as obj => move |area, cr, width, height| {
+                obj.draw_days_header(area, cr, width as f64, height as f64)
+                    .expect("Failed to draw calendar");
+            }),
+        );
+        imp.timeline.set_draw_func(
+            glib::clone!(@weak self as obj => move |area, cr, width, _height| {
+                obj.draw_timeline(area, cr, width as f64)
+                    .expect("Failed to draw calendar");
+            }),
+        );
+
+        // Working hours are visible when the window opens
+        imp.scrolled_window.vadjustment().connect_upper_notify(
+            glib::clone!(@weak self as obj => move |adjustment| {
+                if !obj.imp().scrolled.replace(true) {
+                    adjustment.set_value(8.0 * HOUR_HEIGHT);
+                }
+            }),
+        );
+    }
+
+    fn add_controllers(&self) {
+        let imp = self.imp();
+        let drag_gesture = gtk::GestureDrag::new();
+        drag_gesture.connect_drag_begin(glib::clone!(@weak self as obj => move |_, x, y| {
+            let drag = match obj.block_at(x, y) {
+                Some((block, Some(edge))) => {
+                    let time = match edge {
+                        Edge::Start => block.record.start(),
+                        Edge::End => block.record.end(),
+                    };
+                    Some((block, edge, time))
+                }
+                _ => None,
+            };
+            obj.imp().drag.replace(drag);
+        }));
+        drag_gesture.connect_drag_update(
+            glib::clone!(@weak self as obj => move |_, _offset_x, offset_y| {
+                let imp = obj.imp();
+                let drag = imp.drag.borrow().clone();
+                if let Some((block, edge, time)) = drag {
+                    let record = &block.record;
+                    // Snapped to five minutes
+                    let new_time = time + (offset_y / HOUR_HEIGHT * 3600.0) as i64;
+                    let new_time = (new_time as f64 / 300.0).round() as i64 * 300;
+                    match edge {
+                        Edge::Start => {
+                            let end = record.end();
+                            let start = new_time.min(end - 300);
+                            record.set_start(start);
+                            record.set_duration(end - start);
+                        }
+                        Edge::End => {
+                            record.set_duration(new_time.max(record.start() + 300) - record.start());
+                        }
+                    }
+                    imp.timeline.queue_draw();
+                }
+            }),
+        );
+        drag_gesture.connect_drag_end(
+            glib::clone!(@weak self as obj => move |gesture, offset_x, offset_y| {
+                let imp = obj.imp();
+                if let Some((block, _, _)) = imp.drag.take() {
+                    update_record(&block.record).expect("Failed to update record");
+                    obj.records_changed();
+                } else if offset_x.abs() < 3.0 && offset_y.abs() < 3.0 {
+                    let (x, y) = gesture.start_point().unwrap();
+                    if obj.block_at(x, y).is_none() {
+                        if let Some((_, time)) = obj.point_time(x, y) {
+                            // Snapped to a quarter
+                            obj.new_record(time / 900 * 900);
+                        }
+                    }
+                }
+            }),
+        );
+        imp.timeline.add_controller(drag_gesture);
+
+        let motion_controller = gtk::EventControllerMotion::new();
+        motion_controller.connect_motion(glib::clone!(@weak self as obj => move |_, x, y| {
+            let cursor = match obj.block_at(x, y) {
+                Some((_, Some(_))) => Some("ns-resize"),
+                _ => None,
+            };
+            obj.imp().timeline.set_cursor_from_name(cursor);
+        }));
+        imp.timeline.add_controller(motion_controller);
+
+        imp.timeline.connect_query_tooltip(glib::clone!(
+            @weak self as obj => @default-return false,
+            move |_, x, y, _keyboard, tooltip| {
+                let block = match obj.block_at(x as f64, y as f64) {
+                    Some((block, _)) => block,
+                    None => return false,
+                };
+                let record = &block.record;
+                let start = glib::DateTime::from_unix_local(record.start()).unwrap();
+                let end = glib::DateTime::from_unix_local(record.end()).unwrap();
+                let mut text = format!(
+                    "{} · {}\n{} – {} ({})",
+                    block.task,
+                    block.project,
+                    start.format("%H:%M").unwrap(),
+                    end.format("%H:%M").unwrap(),
+                    Record::duration_display(record.duration())
+                );
+                if !record.note().is_empty() {
+                    text.push('\n');
+                    text.push_str(&record.note());
+                }
+                tooltip.set_text(Some(&text));
+                true
+            }
+        ));
+    }
+
+    fn new_record(&self, start: i64) {
+        let mut tasks: Vec<Task> = vec![];
+        let mut names: Vec<String> = vec![];
+        for project in read_projects(false).expect("Failed to read projects") {
+            for task in
+                read_tasks(project.id(), None, Some(false), None).expect("Failed to read tasks")
+            {
+                names.push(format!("{} · {}", project.name(), task.name()));
+                tasks.push(task);
+            }
+        }
+        if tasks.is_empty() {
+            let toast = adw::Toast::new(&gettext("No tasks to record time for"));
+            self.imp().toast_overlay.add_toast(toast);
+            return;
+        }
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/calendar_record_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let task_drop_down = builder.object::<gtk::DropDown>("task_drop_down").unwrap();
+        let minutes_spin_button = builder
+            .object::<gtk::SpinButton>("minutes_spin_button")
+            .unwrap();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        task_drop_down.set_model(Some(&gtk::StringList::new(&names)));
+        let start_datetime = glib::DateTime::from_unix_local(start).unwrap();
+        // Translators: {} Will be replaced with a date and time.
+        dialog.set_body(
+            &gettext("Starts at {}").replace("{}", &start_datetime.format("%c").unwrap()),
+        );
+        dialog.set_transient_for(Some(self));
+        dialog.connect_response(
+            Some("create"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let task = &tasks[task_drop_down.selected() as usize];
+                let duration = minutes_spin_button.value_as_int() as i64 * 60;
+                create_record(start, task.id(), duration, "").expect("Failed to create record");
+                obj.records_changed();
+            }),
+        );
+        dialog.present();
+    }
+
+    fn records_changed(&self) {
+        self.load();
+        if let Some(app_window) = self.transient_for().and_downcast::<IPlanWindow>() {
+            app_window
+                .activate_action("project.open", None)
+                .expect("Failed to send project.open action");
+        }
+    }
+
+    fn draw_days_header(
+        &self,
+        area: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        let color = area.color();
+        let column_width = (width - TIME_WIDTH) / self.days() as f64;
+        let today = today().to_unix();
+        cr.set_font_size(12.0);
+        for column in 0..self.days() {
+            let day_start = self.day_start(column);
+            let alpha = if day_start == today { 1.0 } else { 0.6 };
+            cr.set_source_rgba(
+                color.red() as f64,
+                color.green() as f64,
+                color.blue() as f64,
+                alpha,
+            );
+            let label = glib::DateTime::from_unix_local(day_start)
+                .unwrap()
+                .format("%a %e")
+                .unwrap();
+            cr.move_to(
+                TIME_WIDTH + column as f64 * column_width + 6.0,
+                height - 8.0,
+            );
+            cr.show_text(&label)?;
+        }
+        Ok(())
+    }
+
+    fn draw_timeline(
+        &self,
+        area: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        width: f64,
+    ) -> Result<(), cairo::Error> {
+        let color = area.color();
+        let (red, green, blue) = (
+            color.red() as f64,
+            color.green() as f64,
+            color.blue() as f64,
+        );
+        let column_width = self.column_width();
+
+        // Grid
+        cr.set_line_width(1.0);
+        cr.set_font_size(10.0);
+        for hour in 0..24 {
+            let y = hour as f64 * HOUR_HEIGHT + 0.5;
+            cr.set_source_rgba(red, green, blue, 0.1);
+            cr.move_to(TIME_WIDTH, y);
+            cr.line_to(width, y);
+            cr.stroke()?;
+            cr.set_source_rgba(red, green, blue, 0.6);
+            cr.move_to(6.0, y + 12.0);
+            cr.show_text(&format!("{hour:02}:00"))?;
+        }
+        cr.set_source_rgba(red, green, blue, 0.1);
+        for column in 0..self.days() {
+            let x = TIME_WIDTH + column as f64 * column_width + 0.5;
+            cr.move_to(x, 0.0);
+            cr.line_to(x, 24.0 * HOUR_HEIGHT);
+        }
+        cr.stroke()?;
+
+        // Records
+        cr.set_font_size(11.0);
+        for (block, column, start, end) in self.segments() {
+            let x = TIME_WIDTH + column as f64 * column_width + 2.0;
+            let top = self.time_y(start, column);
+            let height = self.time_y(end, column) - top;
+            let (block_red, block_green, block_blue) = block.color;
+            cr.set_source_rgba(block_red, block_green, block_blue, 0.85);
+            cr.rectangle(x, top + 1.0, column_width - 4.0, (height - 2.0).max(2.0));
+            cr.fill()?;
+            if height > 14.0 {
+                cr.save()?;
+                cr.rectangle(x, top, column_width - 4.0, height);
+                cr.clip();
+                cr.set_source_rgb(1.0, 1.0, 1.0);
+                cr.move_to(x + 4.0, top + 13.0);
+                cr.show_text(&block.task)?;
+                cr.restore()?;
+            }
+        }
+
+        // Current time
+        let now = glib::DateTime::now_local().unwrap().to_unix();
+        for column in 0..self.days() {
+            if now >= self.day_start(column) && now < self.day_start(column + 1) {
+                let y = self.time_y(now, column);
+                let x = TIME_WIDTH + column as f64 * column_width;
+                // Error color of Adwaita
+                cr.set_source_rgb(0.88, 0.11, 0.14);
+                cr.set_line_width(2.0);
+                cr.move_to(x, y);
+                cr.line_to(x + column_width, y);
+                cr.stroke()?;
+            }
+        }
+        Ok(())
+    }
+
+    #[template_callback]
+    fn handle_previous_button_clicked(&self, _button: gtk::Button) {
+        self.imp().start.set(self.day_start(-self.days()));
+        self.load();
+    }
+
+    #[template_callback]
+    fn handle_next_button_clicked(&self, _button: gtk::Button) {
+        self.imp().start.set(self.day_start(self.days()));
+        self.load();
+    }
+
+    #[template_callback]
+    fn handle_today_button_clicked(&self, _button: gtk::Button) {
+        let today = today();
+        let start = if self.days() == 1 {
+            today
+        } else {
+            today.add_days(1 - today.day_of_week()).unwrap()
+        };
+        self.imp().start.set(start.to_unix());
+        self.load();
+    }
+
+    // The week view starts on Monday
+    #[template_callback]
+    fn handle_view_changed(&self) {
+        let imp = self.imp();
+        let start = glib::DateTime::from_unix_local(imp.start.get()).unwrap();
+        if self.days() == 7 {
+            let monday = start.add_days(1 - start.day_of_week()).unwrap();
+            imp.start.set(monday.to_unix());
+        } else {
+            let today = today().to_unix();
+            if today >= imp.start.get() && today < self.day_start(7) {
+                imp.start.set(today);
+            }
+        }
+        self.load();
+    }
+}
+
+fn today() -> glib::DateTime {
+    let now = glib::DateTime::now_local().unwrap();
+    glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap()
+}
@@ -4,12 +4,33 @@ pub use window::IPlanWindow;
 mod backup_window;
 pub use backup_window::BackupWindow;
 
+mod preferences_window;
+pub use preferences_window::PreferencesWindow;
+
 mod date_row;
 pub use date_row::DateRow;
 
 mod time_row;
 pub use time_row::TimeRow;
 
+mod calendar_window;
+pub use calendar_window::CalendarWindow;
+
+mod quick_add_window;
+pub use quick_add_window::QuickAddWindow;
+
+mod statistics_window;
+pub use statistics_window::StatisticsWindow;
+
+mod time_conflicts_window;
+pub use time_conflicts_window::TimeConflictsWindow;
+
+mod smart_view;
+pub use smart_view::{SmartList, SmartView};
+
+mod timer_indicator;
+pub use timer_indicator::TimerIndicator;
+
 pub mod project;
 pub mod search;
 pub mod sidebar;
@@ -0,0 +1,127 @@
+use adw::subclass::prelude::*;
+use gtk::{gio, glib, prelude::*};
+
+use crate::IPlanApplication;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/preferences_window.ui")]
+    pub struct PreferencesWindow {
+        #[template_child]
+        pub stale_timer_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub idle_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub idle_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub goal_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub goal_period_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub pomodoro_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub work_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub short_break_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub long_break_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub long_break_interval_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub track_breaks_switch: TemplateChild<gtk::Switch>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PreferencesWindow {
+        const NAME: &'static str = "PreferencesWindow";
+        type Type = super::PreferencesWindow;
+        type ParentType = adw::PreferencesWindow;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for PreferencesWindow {}
+    impl WidgetImpl for PreferencesWindow {}
+    impl WindowImpl for PreferencesWindow {}
+    impl AdwWindowImpl for PreferencesWindow {}
+    impl PreferencesWindowImpl for PreferencesWindow {}
+}
+
+glib::wrapper! {
+    pub struct PreferencesWindow(ObjectSubclass<imp::PreferencesWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window, adw::PreferencesWindow,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+impl PreferencesWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        let imp = win.imp();
+        let settings = gio::Settings::new("ir.imansalmani.IPlan.Preferences");
+        settings
+            .bind(
+                "stale-timer-hours",
+                &imp.stale_timer_spin_button.get(),
+                "value",
+            )
+            .build();
+        settings
+            .bind("idle-detection", &imp.idle_switch.get(), "active")
+            .build();
+        settings
+            .bind("idle-minutes", &imp.idle_spin_button.get(), "value")
+            .build();
+        settings
+            .bind("idle-detection", &imp.idle_spin_button.get(), "sensitive")
+            .get_only()
+            .build();
+        settings
+            .bind("goal-hours", &imp.goal_spin_button.get(), "value")
+            .build();
+        settings
+            .bind("goal-period", &imp.goal_period_combo_row.get(), "selected")
+            .build();
+        settings
+            .bind("pomodoro", &imp.pomodoro_switch.get(), "active")
+            .build();
+        settings
+            .bind(
+                "pomodoro-track-breaks",
+                &imp.track_breaks_switch.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind("pomodoro", &imp.track_breaks_switch.get(), "sensitive")
+            .get_only()
+            .build();
+        let spin_buttons = [
+            ("pomodoro-work", imp.work_spin_button.get()),
+            ("pomodoro-short-break", imp.short_break_spin_button.get()),
+            ("pomodoro-long-break", imp.long_break_spin_button.get()),
+            (
+                "pomodoro-long-break-interval",
+                imp.long_break_interval_spin_button.get(),
+            ),
+        ];
+        for (key, spin_button) in spin_buttons {
+            settings.bind(key, &spin_button, "value").build();
+            settings
+                .bind("pomodoro", &spin_button, "sensitive")
+                .get_only()
+                .build();
+        }
+        win
+    }
+}
@@ -0,0 +1,258 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{gio, glib};
+use std::cell::RefCell;
+use std::fs;
+
+use crate::db::models::Project;
+use crate::db::operations::update_project;
+use crate::exchange::invoice::{hours_display, Invoice};
+use crate::views::{project::print_markup, DateRow, IPlanWindow};
+
+// Minutes of the rounding choices
+const ROUNDING_STEPS: [i64; 5] = [0, 6, 15, 30, 60];
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/project/invoice_window.ui")]
+    pub struct InvoiceWindow {
+        pub project: RefCell<Project>,
+        // Records of the period, read again only when the period or rounding changes
+        pub invoice: RefCell<Option<Invoice>>,
+        pub settings: RefCell<Option<gio::Settings>>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub start_date_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub end_date_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub number_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub currency_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub rounding_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub sender_buffer: TemplateChild<gtk::TextBuffer>,
+        #[template_child]
+        pub client_buffer: TemplateChild<gtk::TextBuffer>,
+        #[template_child]
+        pub total_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub total_label: TemplateChild<gtk::Label>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for InvoiceWindow {
+        const NAME: &'static str = "InvoiceWindow";
+        type Type = super::InvoiceWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for InvoiceWindow {}
+    impl WidgetImpl for InvoiceWindow {}
+    impl WindowImpl for InvoiceWindow {
+        // The client is saved once instead of on every keystroke
+        fn close_request(&self) -> glib::signal::Inhibit {
+            let project = self.project.borrow();
+            let buffer = &self.client_buffer;
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true);
+            if project.client() != text {
+                project.set_client(text.to_string());
+                update_project(&project).expect("Failed to update project");
+            }
+            self.parent_close_request()
+        }
+    }
+    impl AdwWindowImpl for InvoiceWindow {}
+}
+
+glib::wrapper! {
+    pub struct InvoiceWindow(ObjectSubclass<imp::InvoiceWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl InvoiceWindow {
+    pub fn new(application: gtk::Application, app_window: &IPlanWindow, project: Project) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        let imp = win.imp();
+        imp.client_buffer.set_text(&project.client());
+        imp.project.replace(project);
+
+        let settings = gio::Settings::new("ir.imansalmani.IPlan.Preferences");
+        imp.number_entry_row
+            .set_text(&settings.int("invoice-number").to_string());
+        settings
+            .bind("invoice-currency", &imp.currency_entry_row.get(), "text")
+            .build();
+        settings
+            .bind("invoice-sender", &imp.sender_buffer.get(), "text")
+            .build();
+        let rounding = settings.int("invoice-rounding") as i64;
+        let position = ROUNDING_STEPS
+            .iter()
+            .position(|step| *step == rounding)
+            .unwrap_or(0);
+        imp.settings.replace(Some(settings));
+        imp.rounding_combo_row.set_selected(position as u32);
+
+        // The current month until today
+        let today = glib::DateTime::now_local().unwrap();
+        imp.start_date_row
+            .set_date(today.year() as u16, today.month() as u8, 1);
+        imp.end_date_row.set_date(
+            today.year() as u16,
+            today.month() as u8,
+            today.day_of_month() as u8,
+        );
+        win.load_invoice();
+        win
+    }
+
+    fn settings(&self) -> gio::Settings {
+        self.imp().settings.borrow().clone().unwrap()
+    }
+
+    fn load_invoice(&self) {
+        let imp = self.imp();
+        if imp.settings.borrow().is_none() {
+            return;
+        }
+        let rounding = ROUNDING_STEPS[imp.rounding_combo_row.selected() as usize];
+        let invoice = Invoice::new(
+            &imp.project.borrow(),
+            imp.start_date_row.calculate_datetime(),
+            imp.end_date_row.calculate_datetime(),
+            rounding,
+        )
+        .expect("Failed to create invoice");
+        imp.invoice.replace(Some(invoice));
+        self.update_total();
+    }
+
+    fn invoice(&self) -> Invoice {
+        let imp = self.imp();
+        let mut invoice = imp.invoice.borrow().clone().unwrap();
+        invoice.number = imp.number_entry_row.text().trim().to_string();
+        invoice.currency = imp.currency_entry_row.text().trim().to_string();
+        let sender = &imp.sender_buffer;
+        invoice.sender = sender
+            .text(&sender.start_iter(), &sender.end_iter(), true)
+            .to_string();
+        let client = &imp.client_buffer;
+        invoice.client = client
+            .text(&client.start_iter(), &client.end_iter(), true)
+            .to_string();
+        invoice
+    }
+
+    fn update_total(&self) {
+        let imp = self.imp();
+        if imp.invoice.borrow().is_none() {
+            return;
+        }
+        let invoice = self.invoice();
+        // Translators: {} Will be replaced with a number of hours.
+        imp.total_row
+            .set_subtitle(&gettext("{} hours").replace("{}", &hours_display(invoice.duration())));
+        imp.total_label
+            .set_label(&invoice.amount_display(invoice.total()));
+    }
+
+    // The next invoice continues from the exported number
+    fn invoice_exported(&self, invoice: &Invoice) {
+        if let Ok(number) = invoice.number.parse::<i32>() {
+            self.settings()
+                .set_int("invoice-number", number + 1)
+                .expect("Failed to save invoice number");
+        }
+        let toast = adw::Toast::new(&gettext("Invoice exported"));
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    fn show_error(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    #[template_callback]
+    fn handle_date_changed(&self, _datetime: glib::DateTime, _date_row: DateRow) {
+        self.load_invoice();
+    }
+
+    #[template_callback]
+    fn handle_details_changed(&self, _editable: adw::EntryRow) {
+        self.update_total();
+    }
+
+    #[template_callback]
+    fn handle_rounding_combo_row_selected(
+        &self,
+        _pspec: glib::ParamSpec,
+        combo_row: adw::ComboRow,
+    ) {
+        if let Some(settings) = self.imp().settings.borrow().as_ref() {
+            let rounding = ROUNDING_STEPS[combo_row.selected() as usize];
+            settings
+                .set_int("invoice-rounding", rounding as i32)
+                .expect("Failed to save invoice rounding");
+        }
+        self.load_invoice();
+    }
+
+    #[template_callback]
+    fn handle_export_pdf_activated(&self, _row: adw::ActionRow) {
+        let invoice = self.invoice();
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        dialog.set_initial_name(Some(&invoice.file_name("pdf")));
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    let result = print_markup(&obj, &invoice.file_name("pdf"), invoice.markup(), file.path());
+                    match result {
+                        Ok(_) => obj.invoice_exported(&invoice),
+                        Err(err) => obj.show_error(err.message()),
+                    }
+                }
+            }),
+        );
+    }
+
+    #[template_callback]
+    fn handle_export_html_activated(&self, _row: adw::ActionRow) {
+        let invoice = self.invoice();
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        dialog.set_initial_name(Some(&invoice.file_name("html")));
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    match fs::write(file.path().unwrap(), invoice.html()) {
+                        Ok(_) => obj.invoice_exported(&invoice),
+                        Err(err) => obj.show_error(&err.to_string()),
+                    }
+                }
+            }),
+        );
+    }
+}
@@ -8,7 +8,7 @@ mod project_list;
 pub use project_list::ProjectList;
 
 mod task_row;
-pub use task_row::{TaskRow, TimerStatus};
+pub use task_row::TaskRow;
 
 mod project_edit_window;
 pub use project_edit_window::ProjectEditWindow;
@@ -27,3 +27,9 @@ pub use task_window::TaskWindow;
 
 mod task_page;
 pub use task_page::TaskPage;
+
+mod invoice_window;
+pub use invoice_window::InvoiceWindow;
+
+mod project_print;
+pub use project_print::{print_markup, PrintContent};
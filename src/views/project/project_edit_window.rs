@@ -3,8 +3,12 @@ use gettextrs::gettext;
 use gtk::glib;
 use std::cell::RefCell;
 
-use crate::db::models::Project;
-use crate::db::operations::{delete_project, update_project};
+use crate::db::models::{CalDavBinding, Project};
+use crate::db::operations::{
+    create_caldav_binding, delete_caldav_binding, delete_project, read_caldav_binding,
+    update_caldav_binding, update_project,
+};
+use crate::timer::TimerService;
 use crate::views::IPlanWindow;
 
 mod imp {
@@ -23,7 +27,31 @@ mod imp {
         #[template_child]
         pub description_buffer: TemplateChild<gtk::TextBuffer>,
         #[template_child]
+        pub rate_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub client_expander_row: TemplateChild<adw::ExpanderRow>,
+        #[template_child]
+        pub client_buffer: TemplateChild<gtk::TextBuffer>,
+        #[template_child]
+        pub budget_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub budget_period_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub goal_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub goal_period_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub danger_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
         pub archive_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub caldav_url_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub caldav_username_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub caldav_password_entry_row: TemplateChild<adw::PasswordEntryRow>,
+        #[template_child]
+        pub caldav_policy_combo_row: TemplateChild<adw::ComboRow>,
     }
 
     #[glib::object_subclass]
@@ -62,12 +90,27 @@ impl ProjectEditWindow {
             .build();
         win.set_transient_for(Some(app_window));
         let imp = win.imp();
+        imp.project.replace(project.clone());
         imp.icon_label.set_text(&project.icon());
         imp.name_entry_row.set_text(&project.name());
         let task_description = project.description();
         imp.description_expander_row
             .set_subtitle(&win.description_display(&task_description));
         imp.description_buffer.set_text(&task_description);
+        imp.rate_spin_button.set_value(project.rate());
+        let client = project.client();
+        imp.client_expander_row
+            .set_subtitle(&win.description_display(&client));
+        imp.client_buffer.set_text(&client);
+        imp.budget_spin_button
+            .set_value(project.budget() as f64 / 3600.0);
+        imp.budget_period_combo_row
+            .set_selected(project.budget_period() as u32);
+        imp.goal_spin_button
+            .set_value(project.goal() as f64 / 3600.0);
+        imp.goal_period_combo_row
+            .set_selected(project.goal_period() as u32);
+        imp.danger_group.set_visible(!project.inbox());
         imp.archive_switch.set_active(project.archive());
         imp.archive_switch.connect_state_set(glib::clone!(
         @weak win, @weak project => @default-return gtk::Inhibit(true),
@@ -79,7 +122,16 @@ impl ProjectEditWindow {
             update_project(&project).expect("Failed to update project");
             gtk::Inhibit(false)
         }));
-        imp.project.replace(project);
+        if let Some(binding) =
+            read_caldav_binding(project.id()).expect("Failed to read CalDAV binding")
+        {
+            imp.caldav_url_entry_row.set_text(&binding.url());
+            imp.caldav_username_entry_row.set_text(&binding.username());
+            imp.caldav_password_entry_row
+                .set_text(&binding.password().unwrap_or_default());
+            imp.caldav_policy_combo_row
+                .set_selected(binding.policy() as u32);
+        }
         win
     }
 
@@ -116,16 +168,143 @@ impl ProjectEditWindow {
     #[template_callback]
     fn handle_description_buffer_changed(&self, buffer: gtk::TextBuffer) {
         let imp: &imp::ProjectEditWindow = self.imp();
-        let project = self.imp().project.take();
+        let project = imp.project.borrow();
         let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true);
         imp.description_expander_row
             .set_subtitle(&self.description_display(&text));
-        project.set_property("description", text);
-        update_project(&project).expect("Failed to update task");
+        if project.description() != text {
+            project.set_property("description", text);
+            update_project(&project).expect("Failed to update task");
+        }
+    }
+
+    #[template_callback]
+    fn handle_rate_spin_button_value_changed(&self, spin_button: gtk::SpinButton) {
+        let project = self.imp().project.borrow();
+        if project.rate() != spin_button.value() {
+            project.set_rate(spin_button.value());
+            update_project(&project).expect("Failed to update project");
+        }
+    }
+
+    #[template_callback]
+    fn handle_client_buffer_changed(&self, buffer: gtk::TextBuffer) {
+        let imp = self.imp();
+        let project = imp.project.borrow();
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), true);
+        if project.client() != text {
+            imp.client_expander_row
+                .set_subtitle(&self.description_display(&text));
+            project.set_client(text.to_string());
+            update_project(&project).expect("Failed to update project");
+        }
+    }
+
+    // Connected to both budget rows, so the signal arguments are not used
+    #[template_callback]
+    fn handle_budget_changed(&self) {
+        let imp = self.imp();
+        let project = imp.project.borrow();
+        let budget = (imp.budget_spin_button.value() * 3600.0) as i64;
+        let budget_period = imp.budget_period_combo_row.selected() as u8;
+        if project.budget() != budget || project.budget_period() != budget_period {
+            project.set_budget(budget);
+            project.set_budget_period(budget_period);
+            update_project(&project).expect("Failed to update project");
+            self.transient_for()
+                .unwrap()
+                .activate_action("project.update", None)
+                .expect("Failed to send project.update action");
+        }
+    }
+
+    // Connected to both goal rows, so the signal arguments are not used
+    #[template_callback]
+    fn handle_goal_changed(&self) {
+        let imp = self.imp();
+        let project = imp.project.borrow();
+        let goal = (imp.goal_spin_button.value() * 3600.0) as i64;
+        let goal_period = imp.goal_period_combo_row.selected() as u8;
+        if project.goal() != goal || project.goal_period() != goal_period {
+            project.set_goal(goal);
+            project.set_goal_period(goal_period);
+            update_project(&project).expect("Failed to update project");
+            self.transient_for()
+                .unwrap()
+                .activate_action("project.update", None)
+                .expect("Failed to send project.update action");
+        }
+    }
+
+    fn save_caldav_binding(&self) {
+        let imp = self.imp();
+        let project_id = imp.project.borrow().id();
+        let url = imp.caldav_url_entry_row.text().trim().to_string();
+        let binding = read_caldav_binding(project_id).expect("Failed to read CalDAV binding");
+        if url.is_empty() {
+            if binding.is_some() {
+                delete_caldav_binding(project_id).expect("Failed to delete CalDAV binding");
+            }
+        } else {
+            let username = imp.caldav_username_entry_row.text().to_string();
+            let password = imp.caldav_password_entry_row.text().to_string();
+            let policy = imp.caldav_policy_combo_row.selected() as u8;
+            let result = match binding {
+                // Tasks of another calendar should be uploaded again
+                Some(binding) if binding.url() == url => {
+                    binding.set_username(username);
+                    binding.set_policy(policy);
+                    let result = binding.set_password(&password);
+                    update_caldav_binding(&binding).expect("Failed to update CalDAV binding");
+                    result
+                }
+                _ => {
+                    delete_caldav_binding(project_id).expect("Failed to delete CalDAV binding");
+                    let binding =
+                        CalDavBinding::new(project_id, url, username, String::new(), policy, 0);
+                    let result = binding.set_password(&password);
+                    create_caldav_binding(&binding).expect("Failed to create CalDAV binding");
+                    result
+                }
+            };
+            if let Err(err) = result {
+                if let Some(window) = self.transient_for().and_downcast::<IPlanWindow>() {
+                    let message = gettext("Failed to save password to keyring");
+                    window
+                        .imp()
+                        .toast_overlay
+                        .add_toast(adw::Toast::new(&format!("{message}: {}", err.message())));
+                }
+            }
+        }
+        self.transient_for()
+            .unwrap()
+            .activate_action("project.sync", None)
+            .expect("Failed to send project.sync action");
+    }
+
+    #[template_callback]
+    fn handle_caldav_entry_row_apply(&self, _entry_row: adw::EntryRow) {
+        self.save_caldav_binding();
+    }
+
+    #[template_callback]
+    fn handle_caldav_policy_combo_row_selected(
+        &self,
+        _pspec: glib::ParamSpec,
+        _combo_row: adw::ComboRow,
+    ) {
+        // Policy alone doesn't create a binding
+        if !self.imp().caldav_url_entry_row.text().is_empty() {
+            self.save_caldav_binding();
+        }
     }
 
     #[template_callback]
     fn handle_delete_button_clicked(&self, _button: gtk::Button) {
+        if self.imp().project.borrow().inbox() {
+            return;
+        }
         let dialog = gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/delete_dialog.ui")
             .object::<adw::MessageDialog>("dialog")
             .unwrap();
@@ -139,6 +318,7 @@ impl ProjectEditWindow {
         dialog.connect_response(Some("delete"), move |dialog, response| {
             if response == "delete" {
                 delete_project(project.id(), project.index()).expect("Failed to delete list");
+                TimerService::default().forget_deleted();
                 dialog
                     .transient_for()
                     .unwrap()
@@ -1,9 +1,12 @@
 use adw;
+use gettextrs::gettext;
 use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::Cell;
+use std::f64::consts::PI;
 use std::thread;
 
-use crate::db::models::{Project, Record};
-use crate::db::operations::{read_records, read_tasks, update_project};
+use crate::db::models::{BudgetPeriod, Goal, GoalPeriod, Project, Record};
+use crate::db::operations::update_project;
 use crate::views::IPlanWindow;
 
 mod imp {
@@ -21,6 +24,22 @@ mod imp {
         #[template_child]
         pub name_entry: TemplateChild<gtk::Entry>,
         #[template_child]
+        pub budget_level_bar: TemplateChild<gtk::LevelBar>,
+        #[template_child]
+        pub goal_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub goal_ring: TemplateChild<gtk::DrawingArea>,
+        #[template_child]
+        pub goal_title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_progress_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_streak_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub goal_history_box: TemplateChild<gtk::ListBox>,
+        // Tracked part of the current goal period, from 0 to 1
+        pub goal_progress: Cell<f64>,
+        #[template_child]
         pub duration_button_content: TemplateChild<adw::ButtonContent>,
         #[template_child]
         pub stat_box: TemplateChild<gtk::ListBox>,
@@ -42,7 +61,35 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for ProjectHeader {}
+    impl ObjectImpl for ProjectHeader {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            self.goal_ring.set_draw_func(glib::clone!(@weak obj => move |area, cr, width, height| {
+                let progress = obj.imp().goal_progress.get();
+                let color = area.color();
+                let radius = width.min(height) as f64 / 2.0 - 2.0;
+                let (x, y) = (width as f64 / 2.0, height as f64 / 2.0);
+                cr.set_line_width(3.0);
+                cr.set_source_rgba(
+                    color.red() as f64,
+                    color.green() as f64,
+                    color.blue() as f64,
+                    0.2,
+                );
+                cr.arc(x, y, radius, 0.0, 2.0 * PI);
+                cr.stroke().expect("Failed to draw goal ring");
+                if progress >= 1.0 {
+                    // Success color of Adwaita
+                    cr.set_source_rgb(0.15, 0.64, 0.41);
+                } else {
+                    cr.set_source_rgb(color.red() as f64, color.green() as f64, color.blue() as f64);
+                }
+                cr.arc(x, y, radius, -PI / 2.0, -PI / 2.0 + 2.0 * PI * progress.min(1.0));
+                cr.stroke().expect("Failed to draw goal ring");
+            }));
+        }
+    }
     impl WidgetImpl for ProjectHeader {}
     impl BoxImpl for ProjectHeader {}
 }
@@ -76,6 +123,24 @@ impl ProjectHeader {
         imp.duration_button_content
             .set_label(&Record::duration_display(project.duration()));
 
+        let budget = project.budget();
+        imp.budget_level_bar.set_visible(budget > 0);
+        if budget > 0 {
+            let used = project.budget_used();
+            imp.budget_level_bar
+                .set_value((used as f64 / budget as f64).min(1.0));
+            imp.budget_level_bar.set_tooltip_text(Some(
+                &BudgetPeriod::from_index(project.budget_period()).usage_display(used, budget),
+            ));
+        }
+
+        // The goal of the project replaces the global one
+        let goal = Goal::of_project(project).or_else(Goal::global);
+        imp.goal_button.set_visible(goal.is_some());
+        if let Some(goal) = goal {
+            self.show_goal(&goal);
+        }
+
         let lists = imp.stat_box.observe_children();
         for _i in 0..lists.n_items() {
             if let Some(row) = lists.item(0).and_downcast::<gtk::ListBoxRow>() {
@@ -84,6 +149,56 @@ impl ProjectHeader {
         }
     }
 
+    fn show_goal(&self, goal: &Goal) {
+        let imp = self.imp();
+        let history = goal.history(7);
+        let tracked = history[0].1;
+        imp.goal_progress.set(tracked as f64 / goal.target as f64);
+        imp.goal_ring.queue_draw();
+        imp.goal_title_label.set_label(&goal.title());
+        let progress = match goal.period {
+            GoalPeriod::Day => gettext("{tracked} of {target} today"),
+            GoalPeriod::Week => gettext("{tracked} of {target} this week"),
+        };
+        imp.goal_progress_label.set_label(
+            &progress
+                .replace("{tracked}", &Record::duration_display(tracked))
+                .replace("{target}", &Record::duration_display(goal.target)),
+        );
+        imp.goal_streak_label
+            .set_label(&goal.streak_display(goal.streak()));
+        imp.goal_button
+            .set_tooltip_text(Some(&imp.goal_progress_label.label()));
+
+        while let Some(row) = imp.goal_history_box.row_at_index(0) {
+            imp.goal_history_box.remove(&row);
+        }
+        for (start, tracked) in history {
+            let row = gtk::Box::builder()
+                .spacing(8)
+                .margin_start(6)
+                .margin_end(6)
+                .margin_top(6)
+                .margin_bottom(6)
+                .build();
+            let date_format = match goal.period {
+                GoalPeriod::Day => "%A",
+                GoalPeriod::Week => "%e %b",
+            };
+            row.append(&gtk::Label::new(Some(&start.format(date_format).unwrap())));
+            let duration_label = gtk::Label::builder()
+                .label(Record::duration_display(tracked))
+                .hexpand(true)
+                .halign(gtk::Align::End)
+                .build();
+            row.append(&duration_label);
+            let reached_image = gtk::Image::from_icon_name("object-select-symbolic");
+            reached_image.set_opacity(if tracked >= goal.target { 1.0 } else { 0.0 });
+            row.append(&reached_image);
+            imp.goal_history_box.append(&row);
+        }
+    }
+
     #[template_callback]
     fn handle_name_button_clicked(&self, button: gtk::Button) {
         button.set_visible(false); // Entry visible param binded to this
@@ -117,35 +232,12 @@ impl ProjectHeader {
             .project()
             .id();
         thread::spawn(move || {
-            let now = glib::DateTime::now_local().unwrap();
-            let dates = &mut vec![];
-            dates.push(now.to_unix());
-            let tasks: Vec<crate::db::models::Task> =
-                read_tasks(project_id, None, None, None).expect("Failed to read tasks");
-            for i in 0..7 {
-                let date = glib::DateTime::from_local(
-                    now.year(),
-                    now.month(),
-                    now.day_of_month() - i,
-                    0,
-                    0,
-                    0.0,
-                )
-                .unwrap();
-                let date_unix = date.to_unix();
-                let mut duration = 0;
-                for task in &tasks {
-                    let records =
-                        read_records(task.id(), false, Some(date_unix), Some(dates[(i) as usize]))
-                            .expect("Failed to read records");
-                    for record in records {
-                        duration += record.duration();
-                    }
-                }
+            // A goal without target, only for its per day aggregation
+            let days = Goal::new(0, GoalPeriod::Day, Some(project_id)).history(7);
+            for (date, duration) in days {
                 if duration != 0 {
                     tx.send((date, duration)).unwrap();
                 }
-                dates.push(date_unix);
             }
         });
         rx.attach(
@@ -1,13 +1,16 @@
 use adw::prelude::*;
-use gettextrs::gettext;
-use gtk::{gdk, glib, glib::Properties, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{gdk, gio, glib, glib::Properties, subclass::prelude::*};
 use std::cell::RefCell;
+use std::fs;
 
-use crate::db::models::{List, Task};
+use crate::db::models::{BudgetPeriod, List, Task};
 use crate::db::operations::{
-    create_task, delete_list, new_position, read_list, read_task, read_tasks, update_list,
-    update_task,
+    create_task, delete_list, new_position, read_list, read_lists, read_project, read_task,
+    read_tasks, update_list, update_task,
 };
+use crate::exchange::icalendar;
+use crate::timer::TimerService;
 use crate::views::project::{ProjectDoneTasksWindow, ProjectLayout, TaskRow, TaskWindow};
 use crate::views::IPlanWindow;
 
@@ -32,6 +35,8 @@ mod imp {
         #[template_child]
         pub options_button: TemplateChild<gtk::MenuButton>,
         #[template_child]
+        pub budget_level_bar: TemplateChild<gtk::LevelBar>,
+        #[template_child]
         pub tasks_box: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
@@ -134,6 +139,7 @@ impl ProjectList {
         }
 
         imp.name_entry.buffer().set_text(&list.name());
+        obj.update_budget();
 
         let tasks = read_tasks(list.project(), Some(list.id()), Some(false), Some(0))
             .expect("Failed to read tasks");
@@ -312,7 +318,6 @@ impl ProjectList {
         let row = row.downcast::<TaskRow>().unwrap();
         let modal = TaskWindow::new(&win.application().unwrap(), &win, row.task());
         modal.present();
-        row.cancel_timer();
         modal.connect_close_request(glib::clone!(
             @weak row as obj => @default-return gtk::Inhibit(false),
             move |_| {
@@ -329,6 +334,21 @@ impl ProjectList {
         ));
     }
 
+    pub fn update_budget(&self) {
+        let imp = self.imp();
+        let list = self.list();
+        let budget = list.budget();
+        imp.budget_level_bar.set_visible(budget > 0);
+        if budget > 0 {
+            let used = list.budget_used();
+            imp.budget_level_bar
+                .set_value((used as f64 / budget as f64).min(1.0));
+            imp.budget_level_bar.set_tooltip_text(Some(
+                &BudgetPeriod::from_index(list.budget_period()).usage_display(used, budget),
+            ));
+        }
+    }
+
     #[template_callback]
     fn handle_name_button_clicked(&self, button: gtk::Button) {
         button.set_visible(false); // Entry visible param binded to this
@@ -352,6 +372,7 @@ impl ProjectList {
         let imp = self.imp();
         imp.tasks_box.prepend(&task_ui);
         let task_imp = task_ui.imp();
+        task_imp.parse_name.set(true);
         task_imp.name_button.set_visible(false);
         task_imp.name_entry.grab_focus();
     }
@@ -360,6 +381,21 @@ impl ProjectList {
     fn handle_delete_button_clicked(&self, _button: gtk::Button) {
         let imp = self.imp();
         imp.options_button.popdown();
+        let list = self.list();
+        let window = self.root().and_downcast::<IPlanWindow>().unwrap();
+        let inbox = read_project(list.project())
+            .expect("Failed to read project")
+            .inbox();
+        if inbox
+            && read_lists(list.project())
+                .expect("Failed to read lists")
+                .len()
+                == 1
+        {
+            let toast = adw::Toast::new(&gettext("The last list of the Inbox can't be deleted"));
+            window.imp().toast_overlay.add_toast(toast);
+            return;
+        }
         let dialog = gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/delete_dialog.ui")
             .object::<adw::MessageDialog>("dialog")
             .unwrap();
@@ -374,6 +410,7 @@ impl ProjectList {
             @weak self as obj => move |_dialog, response| {
                 if response == "delete" {
                     delete_list(obj.list().id()).expect("Failed to delete list");
+                    TimerService::default().forget_deleted();
                     let lists_box = obj.parent().and_downcast::<gtk::Box>().unwrap();
                     let placeholder = obj.root()
                         .and_downcast::<IPlanWindow>()
@@ -400,6 +437,78 @@ impl ProjectList {
         window.present();
     }
 
+    #[template_callback]
+    fn handle_budget_button_clicked(&self, _button: gtk::Button) {
+        let imp = self.imp();
+        imp.options_button.popdown();
+        let list = self.list();
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/project/list_budget_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let hours_spin_button = builder
+            .object::<gtk::SpinButton>("hours_spin_button")
+            .unwrap();
+        let period_combo_row = builder.object::<adw::ComboRow>("period_combo_row").unwrap();
+        hours_spin_button.set_value(list.budget() as f64 / 3600.0);
+        period_combo_row.set_selected(list.budget_period() as u32);
+        dialog.set_transient_for(self.root().and_downcast_ref::<gtk::Window>());
+        dialog.connect_response(
+            Some("save"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let list = obj.list();
+                list.set_budget((hours_spin_button.value() * 3600.0) as i64);
+                list.set_budget_period(period_combo_row.selected() as u8);
+                update_list(&list).expect("Failed to update list");
+                obj.update_budget();
+            }),
+        );
+        dialog.present();
+    }
+
+    #[template_callback]
+    fn handle_import_button_clicked(&self, _button: gtk::Button) {
+        let imp = self.imp();
+        imp.options_button.popdown();
+        let win: IPlanWindow = self.root().and_downcast().unwrap();
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Import")));
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("iCalendar"));
+        filter.add_mime_type("text/calendar");
+        let filters = gio::ListStore::new(gtk::FileFilter::static_type());
+        filters.append(&filter);
+        dialog.set_filters(&filters);
+        dialog.open(
+            Some(&win),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj, @weak win => move |file| {
+                if let Ok(file) = file {
+                    let toast_overlay = win.imp().toast_overlay.get();
+                    let text = match fs::read_to_string(file.path().unwrap()) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            toast_overlay.add_toast(adw::Toast::new(&err.to_string()));
+                            return;
+                        }
+                    };
+                    let result = icalendar::import(&text, &obj.list());
+                    // Tasks before a failure are already imported
+                    win.activate_action("project.open", None)
+                        .expect("Failed to send project.open action");
+                    let message = match result {
+                        Ok(count) => {
+                            ngettext("{} task imported", "{} tasks imported", count as u32)
+                                .replace("{}", &count.to_string())
+                        }
+                        Err(err) => gettext("Failed to import tasks: {}")
+                            .replace("{}", &err.to_string()),
+                    };
+                    toast_overlay.add_toast(adw::Toast::new(&message));
+                }
+            }),
+        );
+    }
+
     fn list_drop_target_drop(
         &self,
         _target: &gtk::DropTarget,
@@ -0,0 +1,259 @@
+use gettextrs::gettext;
+use gtk::{gdk, glib, pango, prelude::*};
+use rusqlite::Result;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::db::models::{Project, Record, Task};
+use crate::db::operations::{read_lists, read_records, read_tasks};
+use crate::exchange::markup::{escape, DIM_COLOR};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrintContent {
+    Project,
+    Report,
+}
+
+impl PrintContent {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "report" => PrintContent::Report,
+            _ => PrintContent::Project,
+        }
+    }
+
+    pub fn markup(&self, project: &Project) -> Result<String> {
+        match self {
+            PrintContent::Project => project_markup(project),
+            PrintContent::Report => report_markup(project),
+        }
+    }
+
+    pub fn file_name(&self, project: &Project) -> String {
+        match self {
+            PrintContent::Project => format!("{}.pdf", project.name()),
+            // Translators: {} Will be replaced with the project name.
+            PrintContent::Report => format!(
+                "{}.pdf",
+                gettext("{} Time Report").replace("{}", &project.name())
+            ),
+        }
+    }
+}
+
+fn push_task(markup: &mut String, task: &Task, depth: usize) -> Result<()> {
+    let indent = "    ".repeat(depth);
+    let checkbox = if task.done() { "☑" } else { "☐" };
+    markup.push_str(&format!("{indent}{checkbox} {}", escape(&task.name())));
+    let duration = task.duration_display();
+    if !duration.is_empty() {
+        markup.push_str(&format!(
+            "\t<span foreground=\"{DIM_COLOR}\">{duration}</span>"
+        ));
+    }
+    markup.push('\n');
+    for line in task.description().lines() {
+        markup.push_str(&format!(
+            "{indent}     <span size=\"small\" foreground=\"{DIM_COLOR}\">{}</span>\n",
+            escape(line)
+        ));
+    }
+    for subtask in read_tasks(task.project(), None, None, Some(task.id()))? {
+        push_task(markup, &subtask, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn project_markup(project: &Project) -> Result<String> {
+    let title = format!("{} {}", project.icon(), project.name());
+    let mut markup = format!(
+        "<span size=\"xx-large\" weight=\"bold\">{}</span>\n",
+        escape(title.trim())
+    );
+    let description = project.description();
+    if !description.trim().is_empty() {
+        markup.push_str(&format!(
+            "<span foreground=\"{DIM_COLOR}\">{}</span>\n",
+            escape(description.trim())
+        ));
+    }
+    for list in read_lists(project.id())? {
+        markup.push_str(&format!(
+            "\n<span size=\"x-large\" weight=\"bold\">{}</span>\n",
+            escape(&list.name())
+        ));
+        for task in read_tasks(project.id(), Some(list.id()), None, Some(0))? {
+            push_task(&mut markup, &task, 0)?;
+        }
+    }
+    Ok(markup)
+}
+
+// Tracked time of the last seven days, per day and per task
+fn report_markup(project: &Project) -> Result<String> {
+    let now = glib::DateTime::now_local().unwrap();
+    let today =
+        glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0).unwrap();
+    let days: Vec<glib::DateTime> = (0..7).map(|i| today.add_days(i - 6).unwrap()).collect();
+    let start = days[0].to_unix();
+    let mut day_durations = [0; 7];
+    let mut task_durations = vec![];
+    let mut notes = vec![];
+    for task in read_tasks(project.id(), None, None, None)? {
+        let mut task_duration = 0;
+        // Records starting exactly at midnight of the first day are included
+        for record in read_records(task.id(), false, Some(start - 1), None)? {
+            if !record.note().is_empty() {
+                notes.push((
+                    record.start(),
+                    task.name(),
+                    record.note(),
+                    record.duration(),
+                ));
+            }
+            let day = days
+                .iter()
+                .rposition(|day| day.to_unix() <= record.start())
+                .unwrap_or(0);
+            day_durations[day] += record.duration();
+            task_duration += record.duration();
+        }
+        if task_duration != 0 {
+            task_durations.push((task.name(), task_duration));
+        }
+    }
+    task_durations.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+    notes.sort_by_key(|(start, _, _, _)| *start);
+
+    let mut markup = format!(
+        "<span size=\"xx-large\" weight=\"bold\">{}</span>\n",
+        escape(&gettext("Time Report"))
+    );
+    markup.push_str(&format!(
+        "<span foreground=\"{DIM_COLOR}\">{} · {} – {}</span>\n",
+        escape(&project.name()),
+        days[0].format("%x").unwrap(),
+        today.format("%x").unwrap()
+    ));
+    markup.push_str(&format!(
+        "\n<span size=\"x-large\" weight=\"bold\">{}</span>\n",
+        escape(&gettext("Days"))
+    ));
+    for (day, duration) in days.iter().zip(day_durations) {
+        markup.push_str(&format!(
+            "{}\t{}\n",
+            day.format("%A").unwrap(),
+            Record::duration_display(duration)
+        ));
+    }
+    markup.push_str(&format!(
+        "\n<span size=\"x-large\" weight=\"bold\">{}</span>\n",
+        escape(&gettext("Tasks"))
+    ));
+    for (name, duration) in task_durations {
+        markup.push_str(&format!(
+            "{}\t{}\n",
+            escape(&name),
+            Record::duration_display(duration)
+        ));
+    }
+    if !notes.is_empty() {
+        markup.push_str(&format!(
+            "\n<span size=\"x-large\" weight=\"bold\">{}</span>\n",
+            escape(&gettext("Notes"))
+        ));
+        for (start, task_name, note, duration) in notes {
+            let start = glib::DateTime::from_unix_local(start).unwrap();
+            markup.push_str(&format!(
+                "{} · {}\t{}\n     <span size=\"small\" foreground=\"{DIM_COLOR}\">{}</span>\n",
+                start.format("%x %H:%M").unwrap(),
+                escape(&task_name),
+                Record::duration_display(duration),
+                escape(&note)
+            ));
+        }
+    }
+    markup.push_str(&format!(
+        "\n<b>{}\t{}</b>\n",
+        escape(&gettext("Total")),
+        Record::duration_display(day_durations.iter().sum())
+    ));
+    Ok(markup)
+}
+
+// Prints with the print dialog, or exports a PDF file when export_filename is set
+pub fn print_markup(
+    window: &impl IsA<gtk::Window>,
+    job_name: &str,
+    markup: String,
+    export_filename: Option<PathBuf>,
+) -> Result<gtk::PrintOperationResult, glib::Error> {
+    let operation = gtk::PrintOperation::new();
+    operation.set_job_name(job_name);
+    operation.set_unit(gtk::Unit::Points);
+    operation.set_embed_page_setup(true);
+    let layout: Rc<RefCell<Option<pango::Layout>>> = Rc::new(RefCell::new(None));
+    // Top of each page in the layout
+    let page_tops: Rc<RefCell<Vec<f64>>> = Rc::new(RefCell::new(vec![]));
+
+    operation.connect_begin_print(glib::clone!(
+        @strong layout, @strong page_tops => move |operation, context| {
+        let page_layout = context.create_pango_layout();
+        let width = context.width() * pango::SCALE as f64;
+        page_layout.set_width(width as i32);
+        page_layout.set_wrap(pango::WrapMode::WordChar);
+        let mut tabs = pango::TabArray::new(1, false);
+        tabs.set_tab(0, pango::TabAlign::Left, (width * 0.7) as i32);
+        page_layout.set_tabs(Some(&tabs));
+        page_layout.set_markup(&markup);
+
+        // Lines are moved to the next page instead of being cut
+        let mut tops = vec![0.0];
+        let mut iter = page_layout.iter();
+        loop {
+            let (line_top, line_bottom) = iter.line_yrange();
+            let line_top = line_top as f64 / pango::SCALE as f64;
+            let line_bottom = line_bottom as f64 / pango::SCALE as f64;
+            let page_top = *tops.last().unwrap();
+            if line_bottom - page_top > context.height() && line_top > page_top {
+                tops.push(line_top);
+            }
+            if !iter.next_line() {
+                break;
+            }
+        }
+        operation.set_n_pages(tops.len() as i32);
+        page_tops.replace(tops);
+        layout.replace(Some(page_layout));
+    }));
+
+    operation.connect_draw_page(move |_operation, context, page| {
+        let layout = layout.borrow();
+        let layout = layout.as_ref().unwrap();
+        let page_tops = page_tops.borrow();
+        let top = page_tops[page as usize];
+        let bottom = page_tops
+            .get(page as usize + 1)
+            .copied()
+            .unwrap_or_else(|| layout.pixel_size().1 as f64);
+        let cr = context.cairo_context();
+        cr.rectangle(0.0, 0.0, context.width(), bottom - top);
+        cr.clip();
+        cr.translate(0.0, -top);
+        let snapshot = gtk::Snapshot::new();
+        snapshot.append_layout(layout, &gdk::RGBA::BLACK);
+        if let Some(node) = snapshot.to_node() {
+            node.draw(&cr);
+        }
+    });
+
+    let action = match export_filename {
+        Some(export_filename) => {
+            operation.set_export_filename(export_filename);
+            gtk::PrintOperationAction::Export
+        }
+        None => gtk::PrintOperationAction::PrintDialog,
+    };
+    operation.run(action, Some(window))
+}
@@ -1,6 +1,7 @@
+use adw::prelude::*;
 use gettextrs::gettext;
 use glib::Properties;
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use gtk::{glib, subclass::prelude::*};
 use std::cell::{Cell, RefCell};
 
 use crate::db::models::Record;
@@ -28,6 +29,12 @@ mod imp {
         pub end_date_row: TemplateChild<DateRow>,
         #[template_child]
         pub end_time_row: TemplateChild<TimeRow>,
+        #[template_child]
+        pub overlap_group: TemplateChild<adw::PreferencesGroup>,
+        #[template_child]
+        pub overlap_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub note_entry_row: TemplateChild<adw::EntryRow>,
         #[property(get, set)]
         pub end_datetime: RefCell<i64>,
         #[template_child]
@@ -160,6 +167,16 @@ impl RecordCreateWindow {
             .build();
     }
 
+    fn check_overlaps(&self) {
+        let imp = self.imp();
+        let overlaps = self.record().overlaps();
+        imp.overlap_group.set_visible(!overlaps.is_empty());
+        imp.overlap_row
+            .set_subtitle(&glib::markup_escape_text(&Record::overlaps_display(
+                &overlaps,
+            )));
+    }
+
     #[template_callback]
     fn handle_cancel_button_clicked(&self, _button: gtk::Button) {
         self.close();
@@ -169,8 +186,14 @@ impl RecordCreateWindow {
     fn handle_done_button_clicked(&self, _button: gtk::Button) {
         let record = self.record();
         if record.duration() != 0 {
-            let record = create_record(record.start(), record.task(), record.duration())
-                .expect("Failed to create record");
+            let note = self.imp().note_entry_row.text();
+            let record = create_record(
+                record.start(),
+                record.task(),
+                record.duration(),
+                note.trim(),
+            )
+            .expect("Failed to create record");
             self.transient_for()
                 .and_downcast::<gtk::Window>()
                 .unwrap()
@@ -193,6 +216,7 @@ impl RecordCreateWindow {
             .unwrap();
         let record = self.record();
         record.set_start(datetime.to_unix());
+        self.check_overlaps();
     }
 
     #[template_callback]
@@ -211,6 +235,7 @@ impl RecordCreateWindow {
         )
         .unwrap();
         record.set_start(datetime.to_unix());
+        self.check_overlaps();
     }
 
     #[template_callback]
@@ -225,5 +250,6 @@ impl RecordCreateWindow {
     fn handle_duration_time_changed(&self, time: i32, _: TimeRow) {
         let record = self.record();
         record.set_duration(time as i64);
+        self.check_overlaps();
     }
 }
@@ -24,6 +24,14 @@ mod imp {
         pub start_time_row: TemplateChild<TimeRow>,
         #[template_child]
         pub duration_row: TemplateChild<TimeRow>,
+        #[template_child]
+        pub note_entry_row: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub billable_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub overlap_image: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub select_check_button: TemplateChild<gtk::CheckButton>,
     }
 
     #[glib::object_subclass]
@@ -84,6 +92,8 @@ impl RecordRow {
         imp.start_time_row
             .set_time_from_digits(start.hour(), start.minute(), start.seconds());
         imp.duration_row.set_time(duration as i32);
+        imp.note_entry_row.set_text(&obj.record().note());
+        imp.billable_switch.set_active(obj.record().billable());
         obj
     }
 
@@ -93,7 +103,16 @@ impl RecordRow {
             .expect("Failed to create glib::DateTime from Record::start");
         let duration = record.duration();
 
-        self.set_title(&Record::duration_display(duration));
+        let note = record.note();
+        if note.is_empty() {
+            self.set_title(&Record::duration_display(duration));
+        } else {
+            self.set_title(&format!(
+                "{} · {}",
+                Record::duration_display(duration),
+                glib::markup_escape_text(&note)
+            ));
+        }
 
         let start_date_text = start.format("%B %e").unwrap();
         let end = start.add_seconds(duration as f64).unwrap();
@@ -111,6 +130,27 @@ impl RecordRow {
             end_date_text,
             end.format("%H:%M").unwrap()
         ));
+
+        let overlaps = record.overlaps();
+        let overlap_image = &self.imp().overlap_image;
+        overlap_image.set_visible(!overlaps.is_empty());
+        if !overlaps.is_empty() {
+            overlap_image.set_tooltip_text(Some(&format!(
+                "{}\n{}",
+                gettext("Overlaps other records:"),
+                Record::overlaps_display(&overlaps)
+            )));
+        }
+    }
+
+    pub fn set_selection_mode(&self, selection_mode: bool) {
+        let select_check_button = &self.imp().select_check_button;
+        select_check_button.set_visible(selection_mode);
+        select_check_button.set_active(false);
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.imp().select_check_button.is_active()
     }
 
     fn refresh(&self) {
@@ -174,6 +214,23 @@ impl RecordRow {
         self.refresh();
     }
 
+    #[template_callback]
+    fn handle_note_entry_row_apply(&self, entry_row: adw::EntryRow) {
+        let record = self.record();
+        record.set_note(entry_row.text().trim());
+        update_record(&record).expect("Failed to update record");
+        self.set_labels();
+    }
+
+    #[template_callback]
+    fn handle_billable_switch_active(&self, _pspec: glib::ParamSpec, switch: gtk::Switch) {
+        let record = self.record();
+        if record.billable() != switch.is_active() {
+            record.set_billable(switch.is_active());
+            update_record(&record).expect("Failed to update record");
+        }
+    }
+
     #[template_callback]
     fn handle_delete_button_clicked(&self, _button: gtk::Button) {
         delete_record(self.record().id()).expect("Failed to delete record");
@@ -1,11 +1,15 @@
-use adw::traits::ExpanderRowExt;
-use gtk::{glib, glib::Properties, prelude::*, subclass::prelude::*};
+use adw::prelude::*;
+use gettextrs::gettext;
+use gtk::{glib, glib::Properties, subclass::prelude::*};
 use std::cell::RefCell;
 use std::unimplemented;
 
 use crate::db::models::{Record, Task};
-use crate::db::operations::{create_task, read_record, read_records, read_tasks, update_task};
-use crate::views::project::{RecordCreateWindow, RecordRow, TaskRow};
+use crate::db::operations::{
+    create_task, read_record, read_records, read_tasks, update_record, update_task,
+};
+use crate::views::project::{RecordCreateWindow, RecordRow, TaskRow, TaskWindow};
+use crate::views::{DateRow, TimeRow};
 
 mod imp {
     use super::*;
@@ -23,6 +27,12 @@ mod imp {
         #[template_child]
         pub description_buffer: TemplateChild<gtk::TextBuffer>,
         #[template_child]
+        pub rate_spin_button: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub due_date_row: TemplateChild<DateRow>,
+        #[template_child]
+        pub due_date_clear_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub lists_menu_button: TemplateChild<gtk::MenuButton>,
         #[template_child]
         pub lists_popover: TemplateChild<gtk::Popover>,
@@ -38,6 +48,8 @@ mod imp {
         pub records_page: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
         pub records_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub select_records_button: TemplateChild<gtk::ToggleButton>,
     }
 
     #[glib::object_subclass]
@@ -113,6 +125,8 @@ impl TaskPage {
         imp.description_expander_row
             .set_subtitle(&obj.description_display(&task_description));
         imp.description_buffer.set_text(&task_description);
+        imp.rate_spin_button.set_value(task.rate());
+        obj.show_due_date();
 
         imp.subtasks_box.set_sort_func(|row1, row2| {
             let task1 = row1.property::<Task>("task");
@@ -166,6 +180,152 @@ impl TaskPage {
         imp.records_box.append(&row);
     }
 
+    fn reload_records(&self) {
+        let imp = self.imp();
+        imp.select_records_button.set_active(false);
+        while let Some(row) = imp.records_box.row_at_index(0) {
+            imp.records_box.remove(&row);
+        }
+        let records =
+            read_records(self.task().id(), false, None, None).expect("Failed to read records");
+        for record in records {
+            imp.records_box.append(&RecordRow::new(record));
+        }
+        imp.task_row.refresh_timer();
+    }
+
+    fn selected_records(&self) -> Vec<Record> {
+        let mut records = vec![];
+        let mut index = 0;
+        while let Some(row) = self.imp().records_box.row_at_index(index) {
+            let row = row.downcast::<RecordRow>().unwrap();
+            if row.is_checked() {
+                records.push(row.record());
+            }
+            index += 1;
+        }
+        records.sort_by_key(Record::start);
+        records
+    }
+
+    fn show_toast(&self, title: &str) {
+        if let Some(window) = self.root().and_downcast::<TaskWindow>() {
+            window.imp().toast_overlay.add_toast(adw::Toast::new(title));
+        }
+    }
+
+    fn split_record(&self, record: Record) {
+        DateRow::static_type();
+        TimeRow::static_type();
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/project/record_split_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let date_row = builder.object::<DateRow>("date_row").unwrap();
+        let time_row = builder.object::<TimeRow>("time_row").unwrap();
+        dialog.set_transient_for(self.root().and_downcast_ref::<gtk::Window>());
+        let middle =
+            glib::DateTime::from_unix_local(record.start() + record.duration() / 2).unwrap();
+        date_row.set_date(
+            middle.year() as u16,
+            middle.month() as u8,
+            middle.day_of_month() as u8,
+        );
+        time_row.set_time_from_digits(middle.hour(), middle.minute(), middle.seconds());
+        dialog.connect_response(
+            Some("split"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let at = date_row
+                    .calculate_datetime()
+                    .add_seconds(time_row.time() as f64)
+                    .unwrap()
+                    .to_unix();
+                if at <= record.start() || at >= record.end() {
+                    obj.show_toast(&gettext("Split time must be inside the record"));
+                    return;
+                }
+                record.split(at).expect("Failed to split record");
+                obj.reload_records();
+            }),
+        );
+        dialog.present();
+    }
+
+    fn move_records(&self, records: Vec<Record>) {
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/project/record_move_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let task_drop_down = builder.object::<gtk::DropDown>("task_drop_down").unwrap();
+        dialog.set_transient_for(self.root().and_downcast_ref::<gtk::Window>());
+        let current_task = self.task();
+        let tasks: Vec<Task> = read_tasks(current_task.project(), None, None, None)
+            .expect("Failed to read tasks")
+            .into_iter()
+            .filter(|task| task.id() != current_task.id())
+            .collect();
+        if tasks.is_empty() {
+            self.show_toast(&gettext("No other tasks in this project"));
+            return;
+        }
+        let names: Vec<String> = tasks.iter().map(Task::name).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        task_drop_down.set_model(Some(&gtk::StringList::new(&names)));
+        dialog.connect_response(
+            Some("move"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let task = &tasks[task_drop_down.selected() as usize];
+                for record in &records {
+                    record.set_task(task.id());
+                    update_record(record).expect("Failed to update record");
+                }
+                obj.reload_records();
+            }),
+        );
+        dialog.present();
+    }
+
+    // For timezone mistakes or a timer that was started late
+    fn shift_records(&self, records: Vec<Record>) {
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/project/record_shift_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let hours_spin_button = builder
+            .object::<gtk::SpinButton>("hours_spin_button")
+            .unwrap();
+        let minutes_spin_button = builder
+            .object::<gtk::SpinButton>("minutes_spin_button")
+            .unwrap();
+        dialog.set_transient_for(self.root().and_downcast_ref::<gtk::Window>());
+        dialog.connect_response(
+            Some("shift"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let offset = hours_spin_button.value_as_int() as i64 * 3600
+                    + minutes_spin_button.value_as_int() as i64 * 60;
+                for record in &records {
+                    record.set_start(record.start() + offset);
+                    update_record(record).expect("Failed to update record");
+                }
+                obj.reload_records();
+            }),
+        );
+        dialog.present();
+    }
+
+    fn show_due_date(&self) {
+        let imp = self.imp();
+        let due_date = self.task().due_date();
+        if due_date > 0 {
+            let date = glib::DateTime::from_unix_local(due_date).unwrap();
+            imp.due_date_row.set_date(
+                date.year() as u16,
+                date.month() as u8,
+                date.day_of_month() as u8,
+            );
+        } else {
+            imp.due_date_row.set_subtitle(&gettext("None"));
+        }
+        imp.due_date_clear_button.set_visible(due_date > 0);
+    }
+
     fn description_display(&self, text: &str) -> String {
         if let Some(first_line) = text.lines().next() {
             return String::from(first_line);
@@ -186,6 +346,33 @@ impl TaskPage {
         }
     }
 
+    #[template_callback]
+    fn handle_rate_spin_button_value_changed(&self, spin_button: gtk::SpinButton) {
+        let task = self.task();
+        if task.rate() != spin_button.value() {
+            task.set_rate(spin_button.value());
+            update_task(&task).expect("Failed to update task");
+        }
+    }
+
+    #[template_callback]
+    fn handle_due_date_changed(&self, datetime: glib::DateTime, _date_row: DateRow) {
+        let task = self.task();
+        task.set_due_date(datetime.to_unix());
+        update_task(&task).expect("Failed to update task");
+        self.imp().task_row.reset(task);
+        self.show_due_date();
+    }
+
+    #[template_callback]
+    fn handle_due_date_clear_button_clicked(&self, _button: gtk::Button) {
+        let task = self.task();
+        task.set_due_date(0);
+        update_task(&task).expect("Failed to update task");
+        self.imp().task_row.reset(task);
+        self.show_due_date();
+    }
+
     #[template_callback]
     fn handle_lists_menu_row_activated(&self, row: gtk::ListBoxRow, _lists_box: gtk::ListBox) {
         let imp = self.imp();
@@ -194,6 +381,7 @@ impl TaskPage {
         match row.index() {
             // Subtasks
             0 => {
+                imp.select_records_button.set_active(false);
                 imp.new_subtask_button.set_visible(true);
                 imp.subtasks_page.set_visible(true);
                 imp.lists_menu_button.set_label(&label.label());
@@ -208,6 +396,73 @@ impl TaskPage {
         }
     }
 
+    #[template_callback]
+    fn handle_select_records_button_toggled(&self, button: gtk::ToggleButton) {
+        let mut index = 0;
+        while let Some(row) = self.imp().records_box.row_at_index(index) {
+            let row = row.downcast::<RecordRow>().unwrap();
+            row.set_selection_mode(button.is_active());
+            index += 1;
+        }
+    }
+
+    #[template_callback]
+    fn handle_split_button_clicked(&self, _button: gtk::Button) {
+        let mut records = self.selected_records();
+        if records.len() != 1 {
+            self.show_toast(&gettext("Select one record to split"));
+            return;
+        }
+        self.split_record(records.remove(0));
+    }
+
+    // Records with another record of the task or a gap between them can't be merged
+    #[template_callback]
+    fn handle_merge_button_clicked(&self, _button: gtk::Button) {
+        let records = self.selected_records();
+        if records.len() < 2 {
+            self.show_toast(&gettext("Select at least two records to merge"));
+            return;
+        }
+        let (first, last) = (records.first().unwrap(), records.last().unwrap());
+        let between = read_records(self.task().id(), false, None, None)
+            .expect("Failed to read records")
+            .into_iter()
+            .filter(|record| record.start() >= first.start() && record.start() <= last.start())
+            .count();
+        if between != records.len() {
+            self.show_toast(&gettext("Only adjacent records can be merged"));
+            return;
+        }
+        // The merged record would count the time between them as tracked
+        if !Record::touching(&records) {
+            self.show_toast(&gettext("Only records that touch or overlap can be merged"));
+            return;
+        }
+        Record::merge(&records).expect("Failed to merge records");
+        self.reload_records();
+    }
+
+    #[template_callback]
+    fn handle_move_button_clicked(&self, _button: gtk::Button) {
+        let records = self.selected_records();
+        if records.is_empty() {
+            self.show_toast(&gettext("Select records to move"));
+            return;
+        }
+        self.move_records(records);
+    }
+
+    #[template_callback]
+    fn handle_shift_button_clicked(&self, _button: gtk::Button) {
+        let records = self.selected_records();
+        if records.is_empty() {
+            self.show_toast(&gettext("Select records to shift"));
+            return;
+        }
+        self.shift_records(records);
+    }
+
     #[template_callback]
     fn handle_new_record_button_clicked(&self, _button: gtk::Button) {
         let win = self.root().and_downcast::<gtk::Window>().unwrap();
@@ -230,7 +485,6 @@ impl TaskPage {
     #[template_callback]
     fn handle_subtasks_box_row_activated(&self, row: gtk::ListBoxRow, _tasks_box: gtk::ListBox) {
         let row = row.downcast::<TaskRow>().unwrap();
-        row.cancel_timer();
         self.activate_action("subtask.open", Some(&row.task().id().to_variant()))
             .expect("Failed to send subtask.open action");
     }
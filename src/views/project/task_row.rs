@@ -1,22 +1,15 @@
 use gettextrs::gettext;
 use gtk::{gdk, glib, glib::Properties, prelude::*, subclass::prelude::*};
 use std::cell::{Cell, RefCell};
-use std::thread;
-use std::time::{Duration, SystemTime};
 
 use crate::db::models::{Record, Task};
-use crate::db::operations::{create_record, delete_task, update_record, update_task};
+use crate::db::operations::{delete_task, new_position, read_lists, update_task};
+use crate::link::Link;
+use crate::quick_add::{estimate_display, QuickAdd};
+use crate::timer::TimerService;
 use crate::views::project::{ProjectDoneTasksWindow, TaskWindow};
 use crate::views::IPlanWindow;
 
-#[derive(Default, PartialEq, Clone, Copy)]
-pub enum TimerStatus {
-    On,
-    #[default]
-    Off,
-    Cancel,
-}
-
 mod imp {
     use super::*;
 
@@ -28,6 +21,8 @@ mod imp {
         pub task: RefCell<Task>,
         #[property(get, set)]
         pub moving_out: Cell<bool>,
+        // New tasks get metadata from their first name, like "tomorrow #tag"
+        pub parse_name: Cell<bool>,
         #[template_child]
         pub checkbox: TemplateChild<gtk::CheckButton>,
         #[template_child]
@@ -38,12 +33,21 @@ mod imp {
         pub name_entry: TemplateChild<gtk::Entry>,
         #[template_child]
         pub name_entry_buffer: TemplateChild<gtk::EntryBuffer>,
-        pub timer_status: Cell<TimerStatus>,
+        #[template_child]
+        pub preview_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub details_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub due_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub timer_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub timer_button_content: TemplateChild<adw::ButtonContent>,
         #[template_child]
+        pub timer_popover: TemplateChild<gtk::Popover>,
+        #[template_child]
+        pub timer_note_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
         pub options_popover: TemplateChild<gtk::Popover>,
     }
 
@@ -70,6 +74,28 @@ mod imp {
             let imp = obj.imp();
 
             obj.add_bindings();
+            imp.timer_popover.set_parent(&imp.timer_button.get());
+
+            // Rows follow the global timer, handlers are dropped with the row
+            let timer_service = TimerService::default();
+            timer_service.connect_closure(
+                "changed",
+                false,
+                glib::closure_local!(@watch obj => move |_: TimerService| {
+                    obj.refresh_timer();
+                }),
+            );
+            timer_service.connect_closure(
+                "tick",
+                false,
+                glib::closure_local!(@watch obj => move |service: TimerService, elapsed: i64| {
+                    if service.task_id() == Some(obj.task().id()) {
+                        obj.imp()
+                            .timer_button_content
+                            .set_label(&Record::duration_display(elapsed));
+                    }
+                }),
+            );
 
             // Cancel name entry on Escape key pressed
             let name_entry_controller = gtk::EventControllerKey::new();
@@ -78,6 +104,7 @@ mod imp {
                     if keycode == 9 {   // Escape key
                         let imp = obj.imp();
                         imp.name_button.set_visible(true);
+                        imp.preview_label.set_visible(false);
                         imp.name_entry.buffer().set_text(obj.task().name());
                     }
                 }),
@@ -85,6 +112,10 @@ mod imp {
             imp.name_entry.add_controller(name_entry_controller);
         }
 
+        fn dispose(&self) {
+            self.timer_popover.unparent();
+        }
+
         fn properties() -> &'static [glib::ParamSpec] {
             Self::derived_properties()
         }
@@ -130,28 +161,61 @@ impl TaskRow {
         let imp = self.imp();
         imp.name_entry_buffer.set_text(task.name());
         self.set_task(task);
-        self.reset_timer();
+        self.refresh_timer();
+        self.refresh_due_date();
+        self.refresh_details();
     }
 
-    pub fn cancel_timer(&self) {
-        self.imp().timer_status.set(TimerStatus::Cancel);
+    fn refresh_details(&self) {
+        let imp = self.imp();
+        let task = self.task();
+        let mut details: Vec<String> = task
+            .tags()
+            .split_whitespace()
+            .map(|tag| format!("#{tag}"))
+            .collect();
+        if task.estimate() > 0 {
+            details.push(estimate_display(task.estimate()));
+        }
+        imp.details_label.set_label(&details.join(" · "));
+        imp.details_label.set_visible(!details.is_empty());
     }
 
-    pub fn reset_timer(&self) {
+    fn refresh_due_date(&self) {
         let imp = self.imp();
         let task = self.task();
-        imp.timer_status.set(TimerStatus::Cancel); // FIXME: Check for removing this
-        if let Some(record) = task.incomplete_record() {
-            record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-            self.start_timer(record);
+        if task.due_date() == 0 || task.done() {
+            imp.due_label.set_visible(false);
+            return;
+        }
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap()
+                .to_unix();
+        let due_date = glib::DateTime::from_unix_local(task.due_date()).unwrap();
+        if task.due_date() == today {
+            imp.due_label.set_label(&gettext("Today"));
+        } else {
+            imp.due_label.set_label(&due_date.format("%e %b").unwrap());
+        }
+        if task.due_date() < today {
+            imp.due_label.add_css_class("error");
         } else {
-            imp.timer_button_content.set_label(&task.duration_display());
+            imp.due_label.remove_css_class("error");
         }
+        imp.due_label.set_visible(true);
     }
 
     pub fn refresh_timer(&self) {
         let imp = self.imp();
-        if imp.timer_status.get() != TimerStatus::On {
+        let timer_service = TimerService::default();
+        if timer_service.task_id() == Some(self.task().id()) {
+            imp.timer_button.add_css_class("destructive-action");
+            imp.timer_button_content
+                .set_label(&Record::duration_display(timer_service.elapsed()));
+        } else {
+            imp.timer_button.remove_css_class("destructive-action");
             imp.timer_button_content
                 .set_label(&self.task().duration_display());
         }
@@ -170,12 +234,14 @@ impl TaskRow {
                     .parent()
                     .and_downcast::<Self>()
                     .unwrap();
-                let imp = obj.imp();
                 let task = obj.task();
                 task.set_done(active);
                 update_task(&task).expect("Failed to update task");
-                if active {
-                    imp.timer_status.set(TimerStatus::Off);
+                obj.refresh_due_date();
+                // Also ends the Pomodoro cycle when the task is done during a break
+                let timer_service = TimerService::default();
+                if active && timer_service.active_task_id() == Some(task.id()) {
+                    timer_service.stop().expect("Failed to stop timer");
                 }
                 obj.activate_action("task.check", Some(&obj.index().to_variant()))
                     .expect("Failed to activate task.check action");
@@ -200,81 +266,113 @@ impl TaskRow {
     #[template_callback]
     fn handle_name_entry_activate(&self, entry: gtk::Entry) {
         let task = self.task();
-        self.imp().name_button.set_visible(true);
-        task.set_name(entry.buffer().text());
+        let imp = self.imp();
+        imp.name_button.set_visible(true);
+        imp.preview_label.set_visible(false);
+        if !imp.parse_name.replace(false) {
+            task.set_name(entry.buffer().text());
+            update_task(&task).expect("Failed to update task");
+            return;
+        }
+
+        let quick_add = QuickAdd::parse(&entry.buffer().text());
+        quick_add.apply(&task);
+        let lists = read_lists(task.project()).expect("Failed to read lists");
+        let target_list = quick_add
+            .find_list(&lists)
+            .filter(|list| list.id() != task.list());
+        if let Some(list) = target_list {
+            task.set_list(list.id());
+            task.set_position(new_position(list.id()));
+        }
         update_task(&task).expect("Failed to update task");
+        imp.name_entry_buffer.set_text(task.name());
+        self.refresh_due_date();
+        self.refresh_details();
+
+        // The row belongs to another list now
+        if target_list.is_some() {
+            if let Some(window) = self.root().and_downcast::<IPlanWindow>() {
+                glib::idle_add_local_once(move || window.reload());
+            }
+        }
     }
 
     #[template_callback]
-    fn handle_name_entry_icon_press(&self, _: gtk::EntryIconPosition) {
+    fn handle_name_entry_changed(&self, entry: gtk::Entry) {
         let imp = self.imp();
-        imp.name_entry.buffer().set_text(self.task().name());
-        imp.name_button.set_visible(true);
+        if !imp.parse_name.get() {
+            return;
+        }
+        let quick_add = QuickAdd::parse(&entry.buffer().text());
+        let lists = if quick_add.list.is_some() {
+            read_lists(self.task().project()).expect("Failed to read lists")
+        } else {
+            Vec::new()
+        };
+        let preview = quick_add.preview(&lists);
+        imp.preview_label.set_label(&preview);
+        imp.preview_label.set_visible(!preview.is_empty());
     }
 
-    fn start_timer(&self, record: Record) {
+    #[template_callback]
+    fn handle_name_entry_icon_press(&self, _: gtk::EntryIconPosition) {
         let imp = self.imp();
-        let button = imp.timer_button.get();
-        imp.timer_status.set(TimerStatus::On);
-        button.add_css_class("destructive-action");
-
-        let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
-        let duration = Duration::from_secs(record.duration() as u64);
-        let start = SystemTime::now().checked_sub(duration).unwrap();
-        thread::spawn(move || loop {
-            if tx.send(start.elapsed().unwrap().as_secs()).is_err() {
-                break;
-            }
-            thread::sleep(Duration::from_secs_f32(0.3));
-        });
-        rx.attach(None,glib::clone!(@weak button, @weak self as obj => @default-return glib::Continue(false),
-            move |duration| {
-                let imp = obj.imp();
-                match imp.timer_status.get() {
-                    TimerStatus::On => {
-                        let button_content = button.child()
-                            .and_downcast::<adw::ButtonContent>()
-                            .unwrap();
-                        button_content.set_label(
-                            &Record::duration_display(duration as i64)
-                        );
-                        glib::Continue(true)
-                    },
-                    TimerStatus::Off => {
-                        button.remove_css_class("destructive-action");
-                        record.set_duration(glib::DateTime::now_local().unwrap().to_unix() - record.start());
-                        update_record(&record).expect("Failed to update record");
-                        imp.timer_button_content.set_label(&obj.task().duration_display());
-                        if obj.parent().is_some() {
-                            obj.activate_action("project.update", None)
-                                .expect("Failed to send project.update");
-                        }
-                        glib::Continue(false)
-                    },
-                    TimerStatus::Cancel => {
-                        button.remove_css_class("destructive-action");
-                        glib::Continue(false)
-                    }
-                }
-            }
-            ),
-        );
+        imp.name_entry.buffer().set_text(self.task().name());
+        imp.name_button.set_visible(true);
+        imp.preview_label.set_visible(false);
     }
 
+    // A running timer asks for a note before stopping, like the header indicator
     #[template_callback]
     fn handle_timer_button_clicked(&self, _button: &gtk::Button) {
         let task = self.task();
-        let record = task.incomplete_record().unwrap_or_else(|| {
-            create_record(glib::DateTime::now_local().unwrap().to_unix(), task.id(), 0)
-                .expect("Failed to create record")
-        });
-        if self.imp().timer_status.get() != TimerStatus::On {
-            self.start_timer(record);
+        let timer_service = TimerService::default();
+        if timer_service.task_id() == Some(task.id()) {
+            self.imp().timer_popover.popup();
         } else {
-            self.imp().timer_status.set(TimerStatus::Off);
+            timer_service
+                .start(task.id())
+                .expect("Failed to start timer");
         }
     }
 
+    #[template_callback]
+    fn handle_timer_popover_show(&self, _popover: gtk::Popover) {
+        let imp = self.imp();
+        imp.timer_note_entry.buffer().set_text("");
+        imp.timer_note_entry.grab_focus();
+    }
+
+    #[template_callback]
+    fn handle_timer_note_entry_activate(&self, _entry: gtk::Entry) {
+        self.stop_timer();
+    }
+
+    #[template_callback]
+    fn handle_timer_stop_button_clicked(&self, _button: gtk::Button) {
+        self.stop_timer();
+    }
+
+    fn stop_timer(&self) {
+        let imp = self.imp();
+        imp.timer_popover.popdown();
+        let timer_service = TimerService::default();
+        if timer_service.task_id() == Some(self.task().id()) {
+            timer_service
+                .stop_with_note(&imp.timer_note_entry.buffer().text())
+                .expect("Failed to stop timer");
+        }
+    }
+
+    #[template_callback]
+    fn handle_copy_link_button_clicked(&self, _button: gtk::Button) {
+        let imp = self.imp();
+        imp.options_popover.popdown();
+        self.clipboard()
+            .set_text(&Link::Task(self.task().id()).uri());
+    }
+
     #[template_callback]
     fn handle_delete_button_clicked(&self, _button: gtk::Button) {
         let task = self.task();
@@ -304,6 +402,9 @@ impl TaskRow {
             move |_toast| {
                 let task = obj.task();
                 if task.suspended() {    // Checking Undo button
+                    TimerService::default()
+                        .release_task(task.id())
+                        .expect("Failed to stop timer");
                     delete_task(task.id(), task.list(), task.position())
                         .expect("Failed to delete task");
                 }
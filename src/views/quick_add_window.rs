@@ -0,0 +1,179 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gtk::glib;
+use std::cell::RefCell;
+
+use crate::db::models::{List, Project};
+use crate::db::operations::{create_task, read_lists, read_projects, update_task};
+use crate::quick_add::QuickAdd;
+use crate::IPlanApplication;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/quick_add_window.ui")]
+    pub struct QuickAddWindow {
+        // Items of the combo rows in the same order, the inbox comes first
+        pub projects: RefCell<Vec<Project>>,
+        pub lists: RefCell<Vec<List>>,
+        #[template_child]
+        pub name_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub preview_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub project_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub list_combo_row: TemplateChild<adw::ComboRow>,
+        #[template_child]
+        pub add_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickAddWindow {
+        const NAME: &'static str = "QuickAddWindow";
+        type Type = super::QuickAddWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for QuickAddWindow {}
+    impl WidgetImpl for QuickAddWindow {}
+    impl WindowImpl for QuickAddWindow {}
+    impl AdwWindowImpl for QuickAddWindow {}
+}
+
+glib::wrapper! {
+    pub struct QuickAddWindow(ObjectSubclass<imp::QuickAddWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl QuickAddWindow {
+    // Not transient for the main window, so it works while the main window is closed
+    pub fn new(application: &IPlanApplication) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        let imp = win.imp();
+
+        let inbox = Project::inbox_project().expect("Failed to read inbox");
+        let mut projects = read_projects(false).expect("Failed to read projects");
+        projects.retain(|project| project.id() != inbox.id());
+        projects.insert(0, inbox);
+        let names: Vec<String> = projects
+            .iter()
+            .map(|project| {
+                format!("{} {}", project.icon(), project.name())
+                    .trim()
+                    .to_string()
+            })
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        imp.projects.replace(projects);
+        imp.project_combo_row
+            .set_model(Some(&gtk::StringList::new(&names)));
+        win.fill_lists();
+
+        // Close on Escape key pressed
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.connect_key_pressed(glib::clone!(
+            @weak win => @default-return gtk::Inhibit(false),
+            move |_controller, _keyval, keycode, _state| {
+                if keycode == 9 {   // Escape key
+                    win.close();
+                    return gtk::Inhibit(true);
+                }
+                gtk::Inhibit(false)
+            }
+        ));
+        win.add_controller(key_controller);
+        imp.name_entry.grab_focus();
+        win
+    }
+
+    fn fill_lists(&self) {
+        let imp = self.imp();
+        let project_id = match imp
+            .projects
+            .borrow()
+            .get(imp.project_combo_row.selected() as usize)
+        {
+            Some(project) => project.id(),
+            None => return,
+        };
+        let lists = read_lists(project_id).expect("Failed to read lists");
+        let names: Vec<String> = lists.iter().map(|list| list.name()).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        imp.lists.replace(lists);
+        imp.list_combo_row
+            .set_model(Some(&gtk::StringList::new(&names)));
+        self.update_preview();
+    }
+
+    // A list typed with >List is selected in the list combo row
+    fn update_preview(&self) {
+        let imp = self.imp();
+        let quick_add = QuickAdd::parse(&imp.name_entry.text());
+        let lists = imp.lists.borrow();
+        if let Some(list) = quick_add.find_list(&lists) {
+            let position = lists.iter().position(|other| other.id() == list.id());
+            imp.list_combo_row.set_selected(position.unwrap() as u32);
+        }
+        let preview = quick_add.preview(&lists);
+        imp.preview_label.set_label(&preview);
+        imp.preview_label.set_visible(!preview.is_empty());
+        let has_list = (imp.list_combo_row.selected() as usize) < lists.len();
+        imp.add_button
+            .set_sensitive(has_list && !quick_add.name.is_empty());
+    }
+
+    fn add_task(&self) {
+        let imp = self.imp();
+        if !imp.add_button.is_sensitive() {
+            return;
+        }
+        let list = imp.lists.borrow()[imp.list_combo_row.selected() as usize].clone();
+        let quick_add = QuickAdd::parse(&imp.name_entry.text());
+        let task = create_task(&quick_add.name, list.project(), list.id(), 0)
+            .expect("Failed to create task");
+        quick_add.apply(&task);
+        update_task(&task).expect("Failed to update task");
+        if let Some(app) = self.application().and_downcast::<IPlanApplication>() {
+            app.reload_windows();
+        }
+        self.close();
+    }
+
+    #[template_callback]
+    fn handle_name_entry_changed(&self, _entry: gtk::Entry) {
+        self.update_preview();
+    }
+
+    #[template_callback]
+    fn handle_name_entry_activate(&self, _entry: gtk::Entry) {
+        self.add_task();
+    }
+
+    #[template_callback]
+    fn handle_add_button_clicked(&self, _button: gtk::Button) {
+        self.add_task();
+    }
+
+    #[template_callback]
+    fn handle_project_combo_row_selected(
+        &self,
+        _pspec: glib::ParamSpec,
+        _combo_row: adw::ComboRow,
+    ) {
+        self.fill_lists();
+    }
+}
@@ -1,9 +1,10 @@
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use adw::prelude::*;
+use gtk::{glib, subclass::prelude::*};
 use std::cell::RefCell;
 
-use crate::db::models::Project;
-use crate::db::operations::{find_projects, find_tasks, read_project};
-use crate::views::search::SearchResult;
+use crate::db::models::{Project, SearchFilter};
+use crate::db::operations::{create_saved_search, find_projects, find_tasks, read_project};
+use crate::views::{search::SearchResult, IPlanWindow};
 
 mod imp {
     use super::*;
@@ -17,6 +18,10 @@ mod imp {
         #[template_child]
         pub show_done_tasks_toggle_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
+        pub filter_drop_down: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub save_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub search_results: TemplateChild<gtk::ListBox>,
         #[template_child]
         pub search_results_placeholder: TemplateChild<adw::StatusPage>,
@@ -109,12 +114,15 @@ impl SearchWindow {
 
         let text = entry.text().to_lowercase();
         let text = text.trim();
+        let filter = self.filter();
+        imp.save_button
+            .set_sensitive(!text.is_empty() || filter != SearchFilter::All);
 
         if text == imp.prev_search.borrow().as_str() {
             return;
         }
 
-        if text.is_empty() {
+        if text.is_empty() && filter == SearchFilter::All {
             let lists = imp.search_results.observe_children();
             for _i in 0..lists.n_items() {
                 if let Some(row) = lists.item(0).and_downcast::<gtk::ListBoxRow>() {
@@ -126,8 +134,13 @@ impl SearchWindow {
         } else {
             imp.search_results_placeholder.set_visible(false);
             let archive = imp.show_done_tasks_toggle_button.is_active();
-            let projects = find_projects(text, archive).expect("Failed to search projects");
-            let tasks = find_tasks(text, archive).expect("Failed to search tasks");
+            // Projects have no tracked time, due date or priority
+            let projects = if filter == SearchFilter::All {
+                find_projects(text, archive).expect("Failed to search projects")
+            } else {
+                vec![]
+            };
+            let tasks = find_tasks(text, archive, filter).expect("Failed to search tasks");
             if imp.search_results.observe_children().n_items()
                 == (projects.len() + tasks.len() + 1) as u32
             {
@@ -173,6 +186,51 @@ impl SearchWindow {
         imp.search_entry.grab_focus();
     }
 
+    #[template_callback]
+    fn handle_filter_drop_down_selected(&self, _pspec: glib::ParamSpec, _drop_down: gtk::DropDown) {
+        let imp = self.imp();
+        imp.prev_search.replace(String::new());
+        self.handle_search_entry_search_changed(imp.search_entry.get());
+        imp.search_entry.grab_focus();
+    }
+
+    #[template_callback]
+    fn handle_save_button_clicked(&self, _button: gtk::Button) {
+        let builder =
+            gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/search/save_search_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let name_entry = builder.object::<gtk::Entry>("name_entry").unwrap();
+        let text = self.imp().search_entry.text().trim().to_string();
+        name_entry.set_text(&text);
+        dialog.set_transient_for(Some(self));
+        dialog.connect_response(
+            Some("save"),
+            glib::clone!(@weak self as obj => move |_dialog, _response| {
+                let imp = obj.imp();
+                let name = name_entry.text().trim().to_string();
+                let name = if name.is_empty() { text.clone() } else { name };
+                let search = create_saved_search(
+                    &name,
+                    &text,
+                    imp.show_done_tasks_toggle_button.is_active(),
+                    imp.filter_drop_down.selected() as u8,
+                )
+                .expect("Failed to create saved search");
+                let app_win = obj.transient_for().and_downcast::<IPlanWindow>().unwrap();
+                app_win.imp().sidebar_projects.refresh_saved_searches();
+                app_win
+                    .activate_action("saved-search.open", Some(&search.id().to_variant()))
+                    .expect("Failed to open saved search");
+                obj.close();
+            }),
+        );
+        dialog.present();
+    }
+
+    fn filter(&self) -> SearchFilter {
+        SearchFilter::from_index(self.imp().filter_drop_down.selected() as u8)
+    }
+
     #[template_callback]
     fn handle_search_results_row_activated(&self, row: SearchResult) {
         let row_imp = row.imp();
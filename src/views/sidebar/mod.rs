@@ -2,4 +2,4 @@ mod sidebar_projects;
 pub use sidebar_projects::SidebarProjects;
 
 mod project_row;
-pub use project_row::ProjectRow;
+pub use project_row::{ProjectRow, SyncStatus};
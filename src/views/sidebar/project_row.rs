@@ -1,3 +1,4 @@
+use gettextrs::gettext;
 use gtk::{gdk, glib, glib::once_cell::sync::Lazy, prelude::*, subclass::prelude::*};
 use std::cell::RefCell;
 
@@ -5,6 +6,15 @@ use crate::db::models::Project;
 use crate::db::operations::read_projects;
 use crate::views::sidebar::SidebarProjects;
 
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum SyncStatus {
+    #[default]
+    Disabled,
+    Syncing,
+    Synced,
+    Failed(String),
+}
+
 mod imp {
     use super::*;
 
@@ -16,6 +26,9 @@ mod imp {
         pub icon_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub sync_image: TemplateChild<gtk::Image>,
+        pub sync_status: RefCell<SyncStatus>,
     }
 
     #[glib::object_subclass]
@@ -95,6 +108,35 @@ impl ProjectRow {
         self.property("project")
     }
 
+    pub fn sync_status(&self) -> SyncStatus {
+        self.imp().sync_status.borrow().clone()
+    }
+
+    pub fn set_sync_status(&self, status: SyncStatus) {
+        let sync_image = &self.imp().sync_image;
+        sync_image.remove_css_class("warning");
+        match &status {
+            SyncStatus::Disabled => sync_image.set_visible(false),
+            SyncStatus::Syncing => {
+                sync_image.set_icon_name(Some("emblem-synchronizing-symbolic"));
+                sync_image.set_tooltip_text(Some(&gettext("Syncing…")));
+                sync_image.set_visible(true);
+            }
+            SyncStatus::Synced => {
+                sync_image.set_icon_name(Some("emblem-ok-symbolic"));
+                sync_image.set_tooltip_text(Some(&gettext("Synced")));
+                sync_image.set_visible(true);
+            }
+            SyncStatus::Failed(error) => {
+                sync_image.set_icon_name(Some("dialog-warning-symbolic"));
+                sync_image.set_tooltip_text(Some(error));
+                sync_image.add_css_class("warning");
+                sync_image.set_visible(true);
+            }
+        }
+        self.imp().sync_status.replace(status);
+    }
+
     #[template_callback]
     fn handle_drag_prepare(&self, _x: f64, _y: f64) -> Option<gdk::ContentProvider> {
         Some(gdk::ContentProvider::for_value(&self.to_value()))
@@ -1,24 +1,39 @@
 use gettextrs::gettext;
 use gtk::{gdk, gio, glib, prelude::*, subclass::prelude::*};
 use std::cell::RefCell;
+use std::thread;
 
-use crate::db::models::Project;
+use crate::caldav;
+use crate::db::models::{Project, SavedSearch};
 use crate::db::operations::{
-    create_list, create_project, new_position, read_lists, read_project, read_projects,
-    update_project, update_task,
+    create_list, create_project, new_position, read_caldav_binding, read_caldav_bindings,
+    read_lists, read_project, read_projects, read_saved_searches, update_project, update_task,
+};
+use crate::timer::TimerService;
+use crate::views::{
+    project::TaskRow,
+    sidebar::{ProjectRow, SyncStatus},
+    IPlanWindow, SmartList, SmartView,
 };
-use crate::views::{project::TaskRow, sidebar::ProjectRow, IPlanWindow};
 mod imp {
     use super::*;
 
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(resource = "/ir/imansalmani/iplan/ui/sidebar/sidebar_projects.ui")]
     pub struct SidebarProjects {
+        #[template_child]
+        pub smart_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub today_row: TemplateChild<gtk::ListBoxRow>,
+        #[template_child]
+        pub today_count_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub archive_toggle_button: TemplateChild<gtk::ToggleButton>,
         #[template_child]
         pub projects_box: TemplateChild<gtk::ListBox>,
         pub projects: RefCell<Option<gio::ListStore>>,
+        // Rows of the smart box after the today row, in the same order
+        pub saved_searches: RefCell<Vec<SavedSearch>>,
     }
 
     #[glib::object_subclass]
@@ -66,15 +81,26 @@ impl SidebarProjects {
     }
 
     pub fn select_active_project(&self) {
+        let imp = self.imp();
+        let window = self.root().unwrap().downcast::<IPlanWindow>().unwrap();
+        if window.showing_smart_list() {
+            imp.projects_box.select_row(None::<&gtk::ListBoxRow>);
+            let row = match window.imp().smart_view.list() {
+                SmartList::Today => Some(imp.today_row.get()),
+                SmartList::Search(search) => imp
+                    .saved_searches
+                    .borrow()
+                    .iter()
+                    .position(|saved_search| saved_search.id() == search.id())
+                    .and_then(|i| imp.smart_box.row_at_index(i as i32 + 1)),
+            };
+            imp.smart_box.select_row(row.as_ref());
+            return;
+        }
+        imp.smart_box.select_row(None::<&gtk::ListBoxRow>);
         // Finding with id because index realtime changes when dragging a project
-        let project_id = self
-            .root()
-            .unwrap()
-            .downcast::<IPlanWindow>()
-            .unwrap()
-            .project()
-            .id();
-        let projects_box = &self.imp().projects_box;
+        let project_id = window.project().id();
+        let projects_box = &imp.projects_box;
         for project_row in projects_box.observe_children().into_iter() {
             let project_row: ProjectRow = project_row.unwrap().downcast().unwrap();
             if project_id == project_row.project().id() {
@@ -84,6 +110,38 @@ impl SidebarProjects {
         }
     }
 
+    pub fn set_today_count(&self, count: usize) {
+        let label = &self.imp().today_count_label;
+        label.set_label(&count.to_string());
+        label.set_visible(count > 0);
+    }
+
+    pub fn refresh_saved_searches(&self) {
+        self.fetch_saved_searches();
+        self.select_active_project();
+    }
+
+    fn fetch_saved_searches(&self) {
+        let imp = self.imp();
+        while let Some(row) = imp.smart_box.row_at_index(1) {
+            imp.smart_box.remove(&row);
+        }
+        let searches = read_saved_searches().expect("Failed to read saved searches");
+        for search in &searches {
+            let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            row_box.append(&gtk::Image::from_icon_name("system-search-symbolic"));
+            row_box.append(
+                &gtk::Label::builder()
+                    .label(search.name())
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .build(),
+            );
+            imp.smart_box
+                .append(&gtk::ListBoxRow::builder().child(&row_box).build());
+        }
+        imp.saved_searches.replace(searches);
+    }
+
     pub fn update_project(&self, project: &Project) {
         let row = self
             .imp()
@@ -133,14 +191,103 @@ impl SidebarProjects {
         }
     }
 
-    fn init_widgets(&self) {
+    pub fn refresh_projects(&self) {
         let imp = self.imp();
+        let rows = imp.projects_box.observe_children();
+        for _i in 0..rows.n_items() {
+            if let Some(row) = rows.item(0).and_downcast::<gtk::ListBoxRow>() {
+                imp.projects_box.remove(&row);
+            }
+        }
+        self.fetch_projects();
+        self.select_active_project();
+    }
 
-        // Fetch
+    fn project_row(&self, project_id: i64) -> Option<ProjectRow> {
+        self.imp()
+            .projects_box
+            .observe_children()
+            .into_iter()
+            .filter_map(|row| row.ok().and_downcast::<ProjectRow>())
+            .find(|row| row.project().id() == project_id)
+    }
+
+    pub fn sync_project(&self, project_id: i64) {
+        let row = match self.project_row(project_id) {
+            Some(row) => row,
+            None => return,
+        };
+        // Database errors are shown like sync errors
+        match read_caldav_binding(project_id) {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                row.set_sync_status(SyncStatus::Disabled);
+                return;
+            }
+            Err(error) => {
+                row.set_sync_status(SyncStatus::Failed(error.to_string()));
+                return;
+            }
+        }
+        if row.sync_status() == SyncStatus::Syncing {
+            return;
+        }
+        row.set_sync_status(SyncStatus::Syncing);
+        let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        thread::spawn(move || {
+            let result = caldav::sync_project(project_id).map_err(|error| error.to_string());
+            tx.send(result).unwrap();
+        });
+        rx.attach(
+            None,
+            glib::clone!(
+            @weak self as obj, @weak row => @default-return glib::Continue(false),
+            move |result| {
+                match result {
+                    Ok(changed) => {
+                        row.set_sync_status(SyncStatus::Synced);
+                        // Tasks deleted on the server may own the running timer
+                        if changed {
+                            TimerService::default().forget_deleted();
+                        }
+                        let window = obj.root().and_downcast::<IPlanWindow>().unwrap();
+                        if changed && window.showing_smart_list() {
+                            window.imp().smart_view.reload();
+                        } else if changed && window.project().id() == project_id {
+                            obj.activate_action("project.open", None)
+                                .expect("Failed to send project.open action");
+                        }
+                    }
+                    Err(error) => row.set_sync_status(SyncStatus::Failed(error)),
+                }
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    pub fn sync_projects(&self) {
+        // The next periodic sync tries again when the bindings can't be read
+        let bindings = read_caldav_bindings().unwrap_or_default();
+        for binding in bindings {
+            self.sync_project(binding.project());
+        }
+    }
+
+    fn fetch_projects(&self) {
+        let imp = self.imp();
         let projects = read_projects(true).expect("Failed to read projects");
         for project in projects {
             imp.projects_box.append(&ProjectRow::new(project));
         }
+    }
+
+    fn init_widgets(&self) {
+        let imp = self.imp();
+
+        // Fetch
+        self.fetch_projects();
+        self.fetch_saved_searches();
+        self.set_today_count(SmartView::today_tasks().len());
 
         // Projcets box filter
         imp.projects_box.set_filter_func(glib::clone!(
@@ -199,13 +346,28 @@ impl SidebarProjects {
     fn handle_projects_box_row_activated(&self, row: gtk::ListBoxRow) {
         let window = self.root().unwrap().downcast::<IPlanWindow>().unwrap();
         let row = row.downcast::<ProjectRow>().unwrap();
-        if window.project().id() != row.project().id() {
+        if window.project().id() != row.project().id() || window.showing_smart_list() {
+            self.imp().smart_box.select_row(None::<&gtk::ListBoxRow>);
             window.set_property("project", row.project().to_value());
             self.activate_action("project.open", None)
                 .expect("Failed to open project");
         }
     }
 
+    #[template_callback]
+    fn handle_smart_box_row_activated(&self, row: gtk::ListBoxRow) {
+        let imp = self.imp();
+        imp.projects_box.select_row(None::<&gtk::ListBoxRow>);
+        if row.index() == 0 {
+            self.activate_action("today.open", None)
+                .expect("Failed to open today");
+        } else {
+            let search_id = imp.saved_searches.borrow()[row.index() as usize - 1].id();
+            self.activate_action("saved-search.open", Some(&search_id.to_variant()))
+                .expect("Failed to open saved search");
+        }
+    }
+
     #[template_callback]
     fn handle_new_button_clicked(&self, _button: gtk::Button) {
         let project = create_project("").expect("Failed to create project");
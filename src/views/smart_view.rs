@@ -0,0 +1,249 @@
+use gettextrs::gettext;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::db::models::{SavedSearch, SearchFilter, Task};
+use crate::db::operations::{
+    delete_saved_search, find_tasks, read_due_tasks, read_project, read_task, read_tracked_tasks,
+};
+use crate::timer::TimerService;
+use crate::views::{
+    project::{TaskRow, TaskWindow},
+    IPlanWindow,
+};
+
+// Task lists that are gathered from all projects
+#[derive(Debug, Default, Clone)]
+pub enum SmartList {
+    #[default]
+    Today,
+    Search(SavedSearch),
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/smart_view.ui")]
+    pub struct SmartView {
+        pub list: RefCell<SmartList>,
+        #[template_child]
+        pub title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub delete_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub empty_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub groups_box: TemplateChild<gtk::Box>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SmartView {
+        const NAME: &'static str = "SmartView";
+        type Type = super::SmartView;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+            // Rows are rebuilt after the check handler of the row returns
+            klass.install_action("task.check", Some("i"), move |obj, _, _value| {
+                obj.queue_reload();
+            });
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for SmartView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            TimerService::default().connect_closure(
+                "changed",
+                false,
+                glib::closure_local!(@watch obj => move |_: TimerService| {
+                    if obj.is_mapped() {
+                        obj.queue_reload();
+                    }
+                }),
+            );
+        }
+    }
+    impl WidgetImpl for SmartView {}
+    impl BoxImpl for SmartView {}
+}
+
+glib::wrapper! {
+    pub struct SmartView(ObjectSubclass<imp::SmartView>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable;
+}
+
+impl Default for SmartView {
+    fn default() -> Self {
+        glib::Object::new::<SmartView>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl SmartView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> SmartList {
+        self.imp().list.borrow().clone()
+    }
+
+    pub fn open(&self, list: SmartList) {
+        let imp = self.imp();
+        match &list {
+            SmartList::Today => {
+                imp.title_label.set_label(&gettext("Today"));
+                imp.empty_page.set_icon_name(Some("object-select-symbolic"));
+                imp.empty_page.set_title(&gettext("Nothing for Today"));
+                imp.empty_page.set_description(Some(&gettext(
+                    "Due and overdue tasks, tasks tracked today and the running timer show here",
+                )));
+            }
+            SmartList::Search(search) => {
+                imp.title_label.set_label(&search.name());
+                imp.empty_page.set_icon_name(Some("system-search-symbolic"));
+                imp.empty_page.set_title(&gettext("No Tasks Found"));
+                imp.empty_page
+                    .set_description(Some(&gettext("Tasks matching this search show here")));
+            }
+        }
+        imp.delete_button
+            .set_visible(matches!(list, SmartList::Search(_)));
+        imp.list.replace(list);
+        self.reload();
+    }
+
+    pub fn tasks(list: &SmartList) -> Vec<Task> {
+        match list {
+            SmartList::Today => Self::today_tasks(),
+            SmartList::Search(search) => find_tasks(
+                &search.text(),
+                search.done(),
+                SearchFilter::from_index(search.filter()),
+            )
+            .expect("Failed to search tasks")
+            .into_iter()
+            .filter(|task| !task.suspended())
+            .collect(),
+        }
+    }
+
+    // The running timer first, then overdue, due today and tracked today tasks
+    pub fn today_tasks() -> Vec<Task> {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        let mut tasks = vec![];
+        if let Some(task_id) = TimerService::default().task_id() {
+            tasks.push(read_task(task_id).expect("Failed to read task"));
+        }
+        tasks.append(
+            &mut read_due_tasks(today.add_days(1).unwrap().to_unix())
+                .expect("Failed to read due tasks"),
+        );
+        tasks.append(&mut read_tracked_tasks(today.to_unix()).expect("Failed to read tasks"));
+        let mut task_ids = HashSet::new();
+        tasks.retain(|task| task_ids.insert(task.id()));
+        tasks
+    }
+
+    pub fn reload(&self) {
+        let imp = self.imp();
+        while let Some(child) = imp.groups_box.first_child() {
+            imp.groups_box.remove(&child);
+        }
+
+        let list = self.list();
+        let tasks = Self::tasks(&list);
+        let count = tasks.len();
+        // Groups keep the order of their first task
+        let mut groups: Vec<(i64, Vec<Task>)> = vec![];
+        for task in tasks {
+            match groups
+                .iter_mut()
+                .find(|(project, _)| *project == task.project())
+            {
+                Some((_, group)) => group.push(task),
+                None => groups.push((task.project(), vec![task])),
+            }
+        }
+        for (project_id, tasks) in groups {
+            let project = read_project(project_id).expect("Failed to read project");
+            let group_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+            let label = gtk::Label::builder()
+                .label(format!("{} {}", project.icon(), project.name()).trim())
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+            label.add_css_class("heading");
+            group_box.append(&label);
+            let tasks_box = gtk::ListBox::builder()
+                .selection_mode(gtk::SelectionMode::None)
+                .build();
+            tasks_box.add_css_class("boxed-list");
+            // Deleted tasks stay suspended until their toast is dismissed
+            tasks_box.set_filter_func(|row| !row.property::<Task>("task").suspended());
+            tasks_box.connect_row_activated(glib::clone!(
+                @weak self as obj => move |_tasks_box, row| obj.open_task(row)));
+            for task in tasks {
+                tasks_box.append(&TaskRow::new(task));
+            }
+            group_box.append(&tasks_box);
+            imp.groups_box.append(&group_box);
+        }
+
+        imp.empty_page.set_visible(count == 0);
+        imp.scrolled_window.set_visible(count != 0);
+        if let (SmartList::Today, Some(window)) = (list, self.root().and_downcast::<IPlanWindow>())
+        {
+            window.imp().sidebar_projects.set_today_count(count);
+        }
+    }
+
+    fn queue_reload(&self) {
+        glib::idle_add_local_once(glib::clone!(@weak self as obj => move || {
+            obj.reload();
+        }));
+    }
+
+    fn open_task(&self, row: &gtk::ListBoxRow) {
+        let win = self.root().and_downcast::<gtk::Window>().unwrap();
+        let row = row.downcast_ref::<TaskRow>().unwrap();
+        let modal = TaskWindow::new(&win.application().unwrap(), &win, row.task());
+        modal.present();
+        modal.connect_close_request(glib::clone!(
+            @weak self as obj => @default-return gtk::Inhibit(false),
+            move |_| {
+                obj.reload();
+                obj.activate_action("project.update", None)
+                    .expect("Failed to send project.update signal");
+                gtk::Inhibit(false)
+            }
+        ));
+    }
+
+    #[template_callback]
+    fn handle_delete_button_clicked(&self, _button: gtk::Button) {
+        if let SmartList::Search(search) = self.list() {
+            delete_saved_search(search.id()).expect("Failed to delete saved search");
+            let window = self.root().and_downcast::<IPlanWindow>().unwrap();
+            window.imp().sidebar_projects.refresh_saved_searches();
+            self.activate_action("today.open", None)
+                .expect("Failed to open today");
+        }
+    }
+}
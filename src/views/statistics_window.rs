@@ -0,0 +1,550 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::{cairo, glib};
+use std::cell::RefCell;
+use std::thread;
+
+use crate::db::models::Record;
+use crate::db::operations::{read_done_times, read_project_durations, read_projects};
+use crate::IPlanApplication;
+
+// Adwaita palette, projects take the colors in turn
+pub const COLORS: [(f64, f64, f64); 8] = [
+    (0.21, 0.52, 0.89),
+    (0.20, 0.82, 0.48),
+    (0.96, 0.83, 0.18),
+    (1.00, 0.47, 0.00),
+    (0.88, 0.11, 0.14),
+    (0.57, 0.25, 0.67),
+    (0.60, 0.76, 0.95),
+    (0.53, 0.44, 0.35),
+];
+const AXIS_WIDTH: f64 = 36.0;
+const LABELS_HEIGHT: f64 = 18.0;
+
+// Aggregated records and completed tasks of the last days
+#[derive(Debug, Default)]
+pub struct Statistics {
+    // Starts of the days and the weeks, the oldest comes first
+    pub days: Vec<i64>,
+    pub weeks: Vec<i64>,
+    pub projects: Vec<(i64, String)>,
+    // Seconds of each project in each day
+    pub daily: Vec<Vec<i64>>,
+    pub done_tasks: Vec<u32>,
+    // Seconds in each hour of each weekday, Monday comes first
+    pub heatmap: [[i64; 24]; 7],
+    pub sessions: usize,
+    pub total: i64,
+}
+
+impl Statistics {
+    pub fn new(days_count: i32) -> Self {
+        let now = glib::DateTime::now_local().unwrap();
+        let today =
+            glib::DateTime::from_local(now.year(), now.month(), now.day_of_month(), 0, 0, 0.0)
+                .unwrap();
+        let monday = today.add_days(1 - now.day_of_week()).unwrap();
+        let mut statistics = Self {
+            days: (0..days_count)
+                .rev()
+                .map(|i| today.add_days(-i).unwrap().to_unix())
+                .collect(),
+            weeks: (0..(days_count + 6) / 7)
+                .rev()
+                .map(|i| monday.add_weeks(-i).unwrap().to_unix())
+                .collect(),
+            ..Default::default()
+        };
+        statistics.daily = vec![vec![]; statistics.days.len()];
+        statistics.done_tasks = vec![0; statistics.weeks.len()];
+
+        let project_names = read_projects(true).expect("Failed to read projects");
+        for (project_id, start, duration) in
+            read_project_durations(statistics.days[0]).expect("Failed to read records")
+        {
+            let project = match statistics
+                .projects
+                .iter()
+                .position(|(id, _)| *id == project_id)
+            {
+                Some(project) => project,
+                None => {
+                    let name = project_names
+                        .iter()
+                        .find(|project| project.id() == project_id)
+                        .map(|project| project.name())
+                        .unwrap_or_default();
+                    statistics.projects.push((project_id, name));
+                    for day in statistics.daily.iter_mut() {
+                        day.push(0);
+                    }
+                    statistics.projects.len() - 1
+                }
+            };
+            let day = statistics
+                .days
+                .iter()
+                .rposition(|day| *day <= start)
+                .unwrap();
+            statistics.daily[day][project] += duration;
+            statistics.sessions += 1;
+            statistics.total += duration;
+            statistics.add_to_heatmap(start, duration);
+        }
+
+        for done_at in read_done_times(statistics.weeks[0]).expect("Failed to read tasks") {
+            let week = statistics
+                .weeks
+                .iter()
+                .rposition(|week| *week <= done_at)
+                .unwrap();
+            statistics.done_tasks[week] += 1;
+        }
+        statistics
+    }
+
+    // Records are split at the hour boundaries
+    fn add_to_heatmap(&mut self, start: i64, duration: i64) {
+        let end = start + duration;
+        let mut time = start;
+        while time < end {
+            let datetime = glib::DateTime::from_unix_local(time).unwrap();
+            let hour_end = time - (datetime.minute() * 60 + datetime.second()) as i64 + 3600;
+            let chunk_end = hour_end.min(end);
+            self.heatmap[datetime.day_of_week() as usize - 1][datetime.hour() as usize] +=
+                chunk_end - time;
+            time = chunk_end;
+        }
+    }
+
+    pub fn average_session(&self) -> i64 {
+        if self.sessions == 0 {
+            0
+        } else {
+            self.total / self.sessions as i64
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/statistics_window.ui")]
+    pub struct StatisticsWindow {
+        pub statistics: RefCell<Statistics>,
+        #[template_child]
+        pub range_drop_down: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub total_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub sessions_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub average_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub projects_chart: TemplateChild<gtk::DrawingArea>,
+        #[template_child]
+        pub projects_legend: TemplateChild<gtk::FlowBox>,
+        #[template_child]
+        pub tasks_chart: TemplateChild<gtk::DrawingArea>,
+        #[template_child]
+        pub heatmap_chart: TemplateChild<gtk::DrawingArea>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for StatisticsWindow {
+        const NAME: &'static str = "StatisticsWindow";
+        type Type = super::StatisticsWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for StatisticsWindow {}
+    impl WidgetImpl for StatisticsWindow {}
+    impl WindowImpl for StatisticsWindow {}
+    impl AdwWindowImpl for StatisticsWindow {}
+}
+
+glib::wrapper! {
+    pub struct StatisticsWindow(ObjectSubclass<imp::StatisticsWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+#[gtk::template_callbacks]
+impl StatisticsWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        win.set_draw_funcs();
+        win.load();
+        win
+    }
+
+    fn set_draw_funcs(&self) {
+        let imp = self.imp();
+        imp.projects_chart.set_draw_func(
+            glib::clone!(@weak self as obj => move |area, cr, width, height| {
+                obj.draw_projects_chart(area, cr, width as f64, height as f64)
+                    .expect("Failed to draw chart");
+            }),
+        );
+        imp.tasks_chart.set_draw_func(
+            glib::clone!(@weak self as obj => move |area, cr, width, height| {
+                obj.draw_tasks_chart(area, cr, width as f64, height as f64)
+                    .expect("Failed to draw chart");
+            }),
+        );
+        imp.heatmap_chart.set_draw_func(
+            glib::clone!(@weak self as obj => move |area, cr, width, height| {
+                obj.draw_heatmap(area, cr, width as f64, height as f64)
+                    .expect("Failed to draw chart");
+            }),
+        );
+
+        imp.projects_chart.connect_query_tooltip(glib::clone!(
+            @weak self as obj => @default-return false,
+            move |area, x, _y, _keyboard, tooltip| {
+                let statistics = obj.imp().statistics.borrow();
+                let index = match bar_index(area, x, statistics.days.len()) {
+                    Some(index) => index,
+                    None => return false,
+                };
+                let mut lines = vec![format_day(statistics.days[index], "%A %e %B")];
+                for (i, (_, name)) in statistics.projects.iter().enumerate() {
+                    let duration = statistics.daily[index][i];
+                    if duration > 0 {
+                        lines.push(format!("{name}: {}", Record::duration_display(duration)));
+                    }
+                }
+                tooltip.set_text(Some(&lines.join("\n")));
+                true
+            }
+        ));
+        imp.tasks_chart.connect_query_tooltip(glib::clone!(
+            @weak self as obj => @default-return false,
+            move |area, x, _y, _keyboard, tooltip| {
+                let statistics = obj.imp().statistics.borrow();
+                let index = match bar_index(area, x, statistics.weeks.len()) {
+                    Some(index) => index,
+                    None => return false,
+                };
+                // Translators: {} Will be replaced with a date.
+                let week = gettext("Week of {}")
+                    .replace("{}", &format_day(statistics.weeks[index], "%e %B"));
+                let count = statistics.done_tasks[index];
+                tooltip.set_text(Some(&format!("{week}: {count}")));
+                true
+            }
+        ));
+        imp.heatmap_chart.connect_query_tooltip(glib::clone!(
+            @weak self as obj => @default-return false,
+            move |area, x, y, _keyboard, tooltip| {
+                let statistics = obj.imp().statistics.borrow();
+                let cell_width = (area.width() as f64 - AXIS_WIDTH) / 24.0;
+                let cell_height = (area.height() as f64 - LABELS_HEIGHT) / 7.0;
+                let hour = ((x as f64 - AXIS_WIDTH) / cell_width).floor();
+                let weekday = (y as f64 / cell_height).floor();
+                if !(0.0..24.0).contains(&hour) || !(0.0..7.0).contains(&weekday) {
+                    return false;
+                }
+                let duration = statistics.heatmap[weekday as usize][hour as usize];
+                tooltip.set_text(Some(&format!(
+                    "{} {:02}:00 · {}",
+                    weekday_name(weekday as i32),
+                    hour,
+                    Record::duration_display(duration)
+                )));
+                true
+            }
+        ));
+    }
+
+    fn load(&self) {
+        let days_count = match self.imp().range_drop_down.selected() {
+            0 => 7,
+            1 => 30,
+            _ => 90,
+        };
+        let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        thread::spawn(move || {
+            tx.send(Statistics::new(days_count)).unwrap();
+        });
+        rx.attach(
+            None,
+            glib::clone!(@weak self as obj => @default-return glib::Continue(false),
+            move |statistics| {
+                obj.show_statistics(statistics);
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn show_statistics(&self, statistics: Statistics) {
+        let imp = self.imp();
+        imp.total_label
+            .set_label(&Record::duration_display(statistics.total));
+        imp.sessions_label
+            .set_label(&statistics.sessions.to_string());
+        imp.average_label
+            .set_label(&Record::duration_display(statistics.average_session()));
+
+        while let Some(child) = imp.projects_legend.child_at_index(0) {
+            imp.projects_legend.remove(&child);
+        }
+        for (i, (_, name)) in statistics.projects.iter().enumerate() {
+            let (red, green, blue) = COLORS[i % COLORS.len()];
+            let label = gtk::Label::builder()
+                .use_markup(true)
+                .label(format!(
+                    "<span foreground=\"#{:02x}{:02x}{:02x}\">●</span> {}",
+                    (red * 255.0) as u8,
+                    (green * 255.0) as u8,
+                    (blue * 255.0) as u8,
+                    glib::markup_escape_text(name)
+                ))
+                .halign(gtk::Align::Start)
+                .build();
+            imp.projects_legend.append(&label);
+        }
+
+        imp.statistics.replace(statistics);
+        imp.projects_chart.queue_draw();
+        imp.tasks_chart.queue_draw();
+        imp.heatmap_chart.queue_draw();
+    }
+
+    fn draw_projects_chart(
+        &self,
+        area: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        let statistics = self.imp().statistics.borrow();
+        let totals: Vec<i64> = statistics
+            .daily
+            .iter()
+            .map(|day| day.iter().sum())
+            .collect();
+        let max = totals.iter().copied().max().unwrap_or(0).max(3600);
+        let hours = (max as f64 / 3600.0).ceil();
+        let chart_height = height - LABELS_HEIGHT;
+        draw_axis(area, cr, width, chart_height, &format!("{hours}h"))?;
+
+        let bar_width = (width - AXIS_WIDTH) / statistics.days.len().max(1) as f64;
+        let label_step = if statistics.days.len() > 7 { 7 } else { 1 };
+        let date_format = if label_step == 1 { "%a" } else { "%e %b" };
+        for (i, day) in statistics.daily.iter().enumerate() {
+            let x = AXIS_WIDTH + i as f64 * bar_width;
+            let mut y = chart_height;
+            for (project, duration) in day.iter().enumerate() {
+                let bar_height = *duration as f64 / (hours * 3600.0) * chart_height;
+                let (red, green, blue) = COLORS[project % COLORS.len()];
+                cr.set_source_rgb(red, green, blue);
+                cr.rectangle(
+                    x + bar_width * 0.15,
+                    y - bar_height,
+                    bar_width * 0.7,
+                    bar_height,
+                );
+                cr.fill()?;
+                y -= bar_height;
+            }
+            if (statistics.days.len() - 1 - i).is_multiple_of(label_step) {
+                draw_label(
+                    area,
+                    cr,
+                    x,
+                    height - 4.0,
+                    &format_day(statistics.days[i], date_format),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_tasks_chart(
+        &self,
+        area: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        let statistics = self.imp().statistics.borrow();
+        let max = statistics
+            .done_tasks
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let chart_height = height - LABELS_HEIGHT;
+        draw_axis(area, cr, width, chart_height, &max.to_string())?;
+
+        let bar_width = (width - AXIS_WIDTH) / statistics.weeks.len().max(1) as f64;
+        let (red, green, blue) = COLORS[0];
+        for (i, count) in statistics.done_tasks.iter().enumerate() {
+            let x = AXIS_WIDTH + i as f64 * bar_width;
+            let bar_height = *count as f64 / max as f64 * chart_height;
+            cr.set_source_rgb(red, green, blue);
+            cr.rectangle(
+                x + bar_width * 0.15,
+                chart_height - bar_height,
+                bar_width * 0.7,
+                bar_height,
+            );
+            cr.fill()?;
+            draw_label(
+                area,
+                cr,
+                x,
+                height - 4.0,
+                &format_day(statistics.weeks[i], "%e %b"),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn draw_heatmap(
+        &self,
+        area: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        let statistics = self.imp().statistics.borrow();
+        let max = statistics
+            .heatmap
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let cell_width = (width - AXIS_WIDTH) / 24.0;
+        let cell_height = (height - LABELS_HEIGHT) / 7.0;
+        let color = area.color();
+        let (red, green, blue) = COLORS[1];
+        for (weekday, hours) in statistics.heatmap.iter().enumerate() {
+            let y = weekday as f64 * cell_height;
+            draw_label(
+                area,
+                cr,
+                0.0,
+                y + cell_height * 0.7,
+                &weekday_name(weekday as i32),
+            )?;
+            for (hour, duration) in hours.iter().enumerate() {
+                let x = AXIS_WIDTH + hour as f64 * cell_width;
+                if *duration == 0 {
+                    cr.set_source_rgba(
+                        color.red() as f64,
+                        color.green() as f64,
+                        color.blue() as f64,
+                        0.05,
+                    );
+                } else {
+                    let alpha = 0.2 + 0.8 * *duration as f64 / max as f64;
+                    cr.set_source_rgba(red, green, blue, alpha);
+                }
+                cr.rectangle(x + 1.0, y + 1.0, cell_width - 2.0, cell_height - 2.0);
+                cr.fill()?;
+            }
+        }
+        for hour in (0..24).step_by(6) {
+            draw_label(
+                area,
+                cr,
+                AXIS_WIDTH + hour as f64 * cell_width,
+                height - 4.0,
+                &format!("{hour:02}:00"),
+            )?;
+        }
+        Ok(())
+    }
+
+    #[template_callback]
+    fn handle_range_changed(&self) {
+        self.load();
+    }
+}
+
+// Baseline and the maximum value of a bar chart
+fn draw_axis(
+    area: &gtk::DrawingArea,
+    cr: &cairo::Context,
+    width: f64,
+    chart_height: f64,
+    max_label: &str,
+) -> Result<(), cairo::Error> {
+    let color = area.color();
+    cr.set_source_rgba(
+        color.red() as f64,
+        color.green() as f64,
+        color.blue() as f64,
+        0.2,
+    );
+    cr.set_line_width(1.0);
+    cr.move_to(AXIS_WIDTH, chart_height + 0.5);
+    cr.line_to(width, chart_height + 0.5);
+    cr.move_to(AXIS_WIDTH, 0.5);
+    cr.line_to(width, 0.5);
+    cr.stroke()?;
+    draw_label(area, cr, 0.0, 10.0, max_label)
+}
+
+fn draw_label(
+    area: &gtk::DrawingArea,
+    cr: &cairo::Context,
+    x: f64,
+    y: f64,
+    text: &str,
+) -> Result<(), cairo::Error> {
+    let color = area.color();
+    cr.set_source_rgba(
+        color.red() as f64,
+        color.green() as f64,
+        color.blue() as f64,
+        0.6,
+    );
+    cr.set_font_size(10.0);
+    cr.move_to(x, y);
+    cr.show_text(text)
+}
+
+// Index of the bar under the pointer
+fn bar_index(area: &gtk::DrawingArea, x: i32, bars: usize) -> Option<usize> {
+    let bar_width = (area.width() as f64 - AXIS_WIDTH) / bars as f64;
+    let index = ((x as f64 - AXIS_WIDTH) / bar_width).floor();
+    (index >= 0.0 && (index as usize) < bars).then_some(index as usize)
+}
+
+fn format_day(unix: i64, format: &str) -> String {
+    glib::DateTime::from_unix_local(unix)
+        .unwrap()
+        .format(format)
+        .unwrap()
+        .to_string()
+}
+
+// Short name of the weekday, zero is Monday
+fn weekday_name(weekday: i32) -> String {
+    // 2024-01-01 was a Monday
+    glib::DateTime::from_local(2024, 1, 1 + weekday, 12, 0, 0.0)
+        .unwrap()
+        .format("%a")
+        .unwrap()
+        .to_string()
+}
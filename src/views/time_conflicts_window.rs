@@ -0,0 +1,157 @@
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::gettext;
+use gtk::glib;
+use rusqlite::Result;
+
+use crate::db::models::Record;
+use crate::db::operations::{
+    create_record, delete_record, read_overlapping_records, read_task, update_record,
+};
+use crate::views::IPlanWindow;
+use crate::IPlanApplication;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/time_conflicts_window.ui")]
+    pub struct TimeConflictsWindow {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub conflicts_box: TemplateChild<gtk::ListBox>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimeConflictsWindow {
+        const NAME: &'static str = "TimeConflictsWindow";
+        type Type = super::TimeConflictsWindow;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TimeConflictsWindow {}
+    impl WidgetImpl for TimeConflictsWindow {}
+    impl WindowImpl for TimeConflictsWindow {}
+    impl AdwWindowImpl for TimeConflictsWindow {}
+}
+
+glib::wrapper! {
+    pub struct TimeConflictsWindow(ObjectSubclass<imp::TimeConflictsWindow>)
+        @extends gtk::Widget, gtk::Window, adw::Window,
+        @implements gtk::Buildable, gtk::Native, gtk::Root;
+}
+
+impl TimeConflictsWindow {
+    pub fn new(application: &IPlanApplication, app_window: &gtk::Window) -> Self {
+        let win: Self = glib::Object::builder()
+            .property("application", application)
+            .build();
+        win.set_transient_for(Some(app_window));
+        win.fill();
+        win
+    }
+
+    fn fill(&self) {
+        let conflicts_box = &self.imp().conflicts_box;
+        while let Some(row) = conflicts_box.row_at_index(0) {
+            conflicts_box.remove(&row);
+        }
+        for (first, second) in read_overlapping_records().expect("Failed to read records") {
+            conflicts_box.append(&self.conflict_row(first, second));
+        }
+    }
+
+    fn conflict_row(&self, first: Record, second: Record) -> adw::ActionRow {
+        let first_task = read_task(first.task()).expect("Failed to read task");
+        let second_task = read_task(second.task()).expect("Failed to read task");
+        let title = if first_task.id() == second_task.id() {
+            first_task.name()
+        } else {
+            format!("{} · {}", first_task.name(), second_task.name())
+        };
+        let overlap = first.end().min(second.end()) - second.start();
+        // Translators: {} Will be replaced with a duration.
+        let overlap_text =
+            gettext("{} counted twice").replace("{}", &Record::duration_display(overlap));
+        let row = adw::ActionRow::builder()
+            .title(glib::markup_escape_text(&title))
+            .subtitle(glib::markup_escape_text(&format!(
+                "{}\n{overlap_text}",
+                Record::overlaps_display(&[first.clone(), second.clone()])
+            )))
+            .build();
+
+        let trim_button = gtk::Button::builder()
+            .label(gettext("Trim"))
+            .tooltip_text(gettext("End the earlier record where the later one starts"))
+            .valign(gtk::Align::Center)
+            .build();
+        trim_button.connect_clicked(glib::clone!(
+            @weak self as obj, @strong first, @strong second => move |_| {
+                obj.resolve(trim(&first, &second));
+            }
+        ));
+        row.add_suffix(&trim_button);
+
+        // Records of different tasks can't become one
+        if first_task.id() == second_task.id() {
+            let merge_button = gtk::Button::builder()
+                .label(gettext("Merge"))
+                .tooltip_text(gettext("Combine both records into one"))
+                .valign(gtk::Align::Center)
+                .build();
+            merge_button.connect_clicked(glib::clone!(
+                @weak self as obj, @strong first, @strong second => move |_| {
+                    obj.resolve(Record::merge(&[first.clone(), second.clone()]));
+                }
+            ));
+            row.add_suffix(&merge_button);
+        }
+        row
+    }
+
+    fn resolve(&self, result: Result<()>) {
+        if let Err(err) = result {
+            let toast = adw::Toast::new(&err.to_string());
+            self.imp().toast_overlay.add_toast(toast);
+        }
+        self.fill();
+        if let Some(app_window) = self.transient_for().and_downcast::<IPlanWindow>() {
+            app_window
+                .activate_action("project.open", None)
+                .expect("Failed to send project.open action");
+        }
+    }
+}
+
+// The time after the later record moves to a new record when it sits inside the earlier one
+fn trim(first: &Record, second: &Record) -> Result<()> {
+    if first.start() == second.start() {
+        if second.end() <= first.end() {
+            return delete_record(second.id());
+        }
+        second.set_duration(second.end() - first.end());
+        second.set_start(first.end());
+        return update_record(second);
+    }
+    if first.end() > second.end() {
+        let rest = create_record(
+            second.end(),
+            first.task(),
+            first.end() - second.end(),
+            &first.note(),
+        )?;
+        rest.set_billable(first.billable());
+        update_record(&rest)?;
+    }
+    first.set_duration(second.start() - first.start());
+    update_record(first)
+}
@@ -0,0 +1,143 @@
+use gettextrs::gettext;
+use gtk::subclass::prelude::*;
+use gtk::{glib, prelude::*};
+
+use crate::db::models::Record;
+use crate::db::operations::read_task;
+use crate::link::Link;
+use crate::timer::{TimerPhase, TimerService};
+use crate::views::IPlanWindow;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(resource = "/ir/imansalmani/iplan/ui/timer_indicator.ui")]
+    pub struct TimerIndicator {
+        #[template_child]
+        pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub elapsed_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stop_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub note_entry: TemplateChild<gtk::Entry>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimerIndicator {
+        const NAME: &'static str = "TimerIndicator";
+        type Type = super::TimerIndicator;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_instance_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TimerIndicator {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            let timer_service = TimerService::default();
+            timer_service.connect_closure(
+                "changed",
+                false,
+                glib::closure_local!(@watch obj => move |_: TimerService| {
+                    obj.refresh();
+                }),
+            );
+            timer_service.connect_closure(
+                "tick",
+                false,
+                glib::closure_local!(@watch obj => move |service: TimerService, elapsed: i64| {
+                    let seconds = service.remaining().unwrap_or(elapsed);
+                    obj.imp()
+                        .elapsed_label
+                        .set_label(&Record::duration_display(seconds));
+                }),
+            );
+            obj.refresh();
+        }
+    }
+    impl WidgetImpl for TimerIndicator {}
+    impl BoxImpl for TimerIndicator {}
+}
+
+glib::wrapper! {
+    pub struct TimerIndicator(ObjectSubclass<imp::TimerIndicator>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Buildable, gtk::Orientable;
+}
+
+impl Default for TimerIndicator {
+    fn default() -> Self {
+        glib::Object::new::<Self>()
+    }
+}
+
+#[gtk::template_callbacks]
+impl TimerIndicator {
+    // Pomodoro sessions and breaks show the remaining time instead
+    fn refresh(&self) {
+        let imp = self.imp();
+        let timer_service = TimerService::default();
+        let task = match timer_service.active_task_id().map(read_task) {
+            Some(Ok(task)) => task,
+            _ => {
+                self.set_visible(false);
+                return;
+            }
+        };
+        let name = match timer_service.phase() {
+            TimerPhase::Work => task.name(),
+            TimerPhase::ShortBreak => gettext("Short Break"),
+            TimerPhase::LongBreak => gettext("Long Break"),
+        };
+        imp.name_label.set_label(&name);
+        let seconds = timer_service
+            .remaining()
+            .unwrap_or_else(|| timer_service.elapsed());
+        imp.elapsed_label
+            .set_label(&Record::duration_display(seconds));
+        self.set_visible(true);
+    }
+
+    #[template_callback]
+    fn handle_task_button_clicked(&self, _button: gtk::Button) {
+        let window = self.root().and_downcast::<IPlanWindow>().unwrap();
+        if let Some(task_id) = TimerService::default().active_task_id() {
+            Link::Task(task_id).open(&window);
+        }
+    }
+
+    #[template_callback]
+    fn handle_stop_popover_show(&self, _popover: gtk::Popover) {
+        let imp = self.imp();
+        imp.note_entry.buffer().set_text("");
+        imp.note_entry.grab_focus();
+    }
+
+    #[template_callback]
+    fn handle_note_entry_activate(&self, _entry: gtk::Entry) {
+        self.stop_timer();
+    }
+
+    #[template_callback]
+    fn handle_stop_button_clicked(&self, _button: gtk::Button) {
+        self.stop_timer();
+    }
+
+    fn stop_timer(&self) {
+        let imp = self.imp();
+        imp.stop_button.popdown();
+        TimerService::default()
+            .stop_with_note(&imp.note_entry.buffer().text())
+            .expect("Failed to stop timer");
+    }
+}
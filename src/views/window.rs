@@ -18,17 +18,30 @@
  * SPDX-License-Identifier: GPL-3.0-or-later
  */
 
-use adw::subclass::prelude::*;
-use gettextrs::gettext;
-use gtk::{gdk, gio, glib, glib::once_cell::sync::Lazy, prelude::*};
+use adw::{prelude::*, subclass::prelude::*};
+use gettextrs::{gettext, ngettext};
+use gtk::{gdk, gio, glib, glib::once_cell::sync::Lazy};
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
 
-use crate::db::models::Project;
-use crate::db::operations::{create_list, create_project, read_list, read_projects};
+use crate::db::models::{BudgetPeriod, Project};
+use crate::db::operations::{
+    create_list, create_project, read_list, read_lists, read_projects, read_saved_search, read_task,
+};
+use crate::exchange::{
+    icalendar,
+    outline::{self, OutlineFormat},
+    todo_txt, ExportFormat,
+};
+use crate::idle;
+use crate::timer::TimerService;
 use crate::views::project::{
-    ProjectDoneTasksWindow, ProjectEditWindow, ProjectHeader, ProjectLayout, ProjectLists,
+    print_markup, InvoiceWindow, PrintContent, ProjectDoneTasksWindow, ProjectEditWindow,
+    ProjectHeader, ProjectLayout, ProjectLists,
 };
 use crate::views::sidebar::SidebarProjects;
+use crate::views::{DateRow, SmartList, SmartView, TimeRow, TimerIndicator};
 
 mod imp {
     use super::*;
@@ -38,6 +51,8 @@ mod imp {
     pub struct IPlanWindow {
         pub settings: RefCell<Option<gio::Settings>>,
         pub project: RefCell<Project>,
+        // Period start and reached threshold of each budget, keyed like "project-1"
+        pub budget_alerts: RefCell<HashMap<String, (Option<i64>, u8)>>,
         #[template_child]
         pub project_layout_button: TemplateChild<gtk::Button>,
         #[template_child]
@@ -45,9 +60,15 @@ mod imp {
         #[template_child]
         pub project_header: TemplateChild<ProjectHeader>,
         #[template_child]
+        pub timer_indicator: TemplateChild<TimerIndicator>,
+        #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
+        pub content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
         pub project_lists: TemplateChild<ProjectLists>,
+        #[template_child]
+        pub smart_view: TemplateChild<SmartView>,
     }
 
     #[glib::object_subclass]
@@ -62,6 +83,7 @@ mod imp {
             klass.install_action("project.open", None, move |win, _, _| {
                 let imp = win.imp();
                 let project = win.project();
+                win.show_project_page();
                 imp.project_header.open_project(&project);
                 imp.project_lists.open_project(project.id());
                 imp.project_lists.select_task(None);
@@ -70,6 +92,7 @@ mod imp {
             klass.install_action("project.new", None, move |win, _, _| {
                 let imp = win.imp();
                 let project = win.project();
+                win.show_project_page();
                 imp.project_header.open_project(&project);
                 imp.project_lists.open_project(project.id());
                 imp.project_header.imp().name_button.emit_clicked();
@@ -79,13 +102,30 @@ mod imp {
                 let window = ProjectEditWindow::new(win.application().unwrap(), win, win.project());
                 window.present();
             });
+            klass.install_action("project.invoice", None, move |win, _, _| {
+                let window = InvoiceWindow::new(win.application().unwrap(), win, win.project());
+                window.present();
+            });
             klass.install_action("project.update", None, move |win, _, _| {
                 let imp = win.imp();
                 let project = win.project();
                 imp.project_header.open_project(&project);
                 imp.sidebar_projects.update_project(&project);
+                imp.sidebar_projects
+                    .set_today_count(SmartView::today_tasks().len());
+            });
+            klass.install_action("today.open", None, move |win, _, _| {
+                win.show_smart_list(SmartList::Today);
+            });
+            klass.install_action("saved-search.open", Some("x"), move |win, _, value| {
+                let search_id = value.unwrap().get::<i64>().unwrap();
+                let search = read_saved_search(search_id).expect("Failed to read saved search");
+                win.show_smart_list(SmartList::Search(search));
             });
             klass.install_action("project.delete", None, move |win, _, _| {
+                if win.project().inbox() {
+                    return;
+                }
                 let projects_section = &win.imp().sidebar_projects;
                 projects_section.delete_project(win.project().index());
                 let projects = read_projects(true).expect("Failed to read projects");
@@ -102,6 +142,41 @@ mod imp {
                 win.activate_action("project.open", None)
                     .expect("Failed to send project.open action");
             });
+            klass.install_action("project.export", Some("s"), move |win, _, value| {
+                let format = value.unwrap().get::<String>().unwrap();
+                if let Some(format) = ExportFormat::from_name(&format) {
+                    win.export_project(format);
+                }
+            });
+            klass.install_action("project.export-all", Some("s"), move |win, _, value| {
+                let format = value.unwrap().get::<String>().unwrap();
+                if let Some(format) = OutlineFormat::from_name(&format) {
+                    win.export_projects(format);
+                }
+            });
+            klass.install_action("project.print", Some("s"), move |win, _, value| {
+                let content = value.unwrap().get::<String>().unwrap();
+                win.print_project(PrintContent::from_name(&content), false);
+            });
+            klass.install_action("project.export-pdf", Some("s"), move |win, _, value| {
+                let content = value.unwrap().get::<String>().unwrap();
+                win.print_project(PrintContent::from_name(&content), true);
+            });
+            klass.add_binding_action(
+                gdk::Key::p,
+                gdk::ModifierType::CONTROL_MASK,
+                "project.print",
+                Some(&"project".to_variant()),
+            );
+            // Only todo.txt files are imported into a whole project
+            klass.install_action("project.import", Some("s"), move |win, _, value| {
+                if value.unwrap().get::<String>().unwrap() == "todo-txt" {
+                    win.import_todo_txt();
+                }
+            });
+            klass.install_action("project.sync", None, move |win, _, _| {
+                win.imp().sidebar_projects.sync_project(win.project().id());
+            });
             klass.install_action("list.new", None, move |win, _, _| {
                 let imp = win.imp();
                 imp.project_lists.new_list(win.project().id());
@@ -109,6 +184,7 @@ mod imp {
             klass.install_action("search.project", None, move |win, _, _| {
                 let imp = win.imp();
                 let project = win.project();
+                win.show_project_page();
                 imp.project_header.open_project(&project);
                 imp.project_lists.open_project(project.id());
                 imp.project_lists.select_task(None);
@@ -118,7 +194,8 @@ mod imp {
             klass.install_action("search.task", Some("(bx)"), move |win, _, value| {
                 let imp = win.imp();
                 let (project_changed, task_id) = value.unwrap().get::<(bool, i64)>().unwrap();
-                if project_changed {
+                if project_changed || win.showing_smart_list() {
+                    win.show_project_page();
                     let project = win.project();
                     imp.project_header.open_project(&project);
                     imp.project_lists.open_project(project.id());
@@ -144,7 +221,8 @@ mod imp {
                 let imp = win.imp();
                 let (project_changed, task_id, list_id) =
                     value.unwrap().get::<(bool, i64, i64)>().unwrap();
-                if project_changed {
+                if project_changed || win.showing_smart_list() {
+                    win.show_project_page();
                     let project = win.project();
                     imp.project_header.open_project(&project);
                     imp.project_lists.open_project(project.id());
@@ -233,6 +311,7 @@ glib::wrapper! {
 #[gtk::template_callbacks]
 impl IPlanWindow {
     pub fn new<P: glib::IsA<gtk::Application>>(application: &P) -> Self {
+        Project::inbox_project().expect("Failed to read inbox");
         let projects = read_projects(true).expect("Failed to read projects");
         let home_project = if let Some(project) = projects.get(0) {
             project.clone()
@@ -265,6 +344,69 @@ impl IPlanWindow {
         imp.sidebar_projects.select_active_project();
         imp.project_lists.open_project(window.project().id());
 
+        // Tracked durations of the project change when a timer stops
+        TimerService::default().connect_closure(
+            "changed",
+            false,
+            glib::closure_local!(@watch window => move |_: TimerService| {
+                window
+                    .activate_action("project.update", None)
+                    .expect("Failed to send project.update action");
+                window.check_budgets();
+            }),
+        );
+
+        // Budgets are checked once a minute while a timer runs
+        window.check_budgets();
+        TimerService::default().connect_closure(
+            "tick",
+            false,
+            glib::closure_local!(@watch window => move |_: TimerService, elapsed: i64| {
+                if elapsed > 0 && elapsed % 60 == 0 {
+                    window.check_budgets();
+                }
+            }),
+        );
+
+        // Forgotten timers are checked on startup and after a suspend
+        window.check_stale_timer(None);
+        TimerService::default().connect_closure(
+            "resumed",
+            false,
+            glib::closure_local!(@watch window => move |_: TimerService, last_tick: i64| {
+                window.check_stale_timer(Some(last_tick));
+            }),
+        );
+
+        TimerService::default().connect_closure(
+            "idle-returned",
+            false,
+            glib::closure_local!(@watch window => move |_: TimerService, idle_start: i64| {
+                window.ask_idle_time(idle_start);
+            }),
+        );
+        // Input in this window for the idle monitor fallback
+        let motion_controller = gtk::EventControllerMotion::new();
+        motion_controller.connect_motion(|_, _, _| idle::report_activity());
+        window.add_controller(motion_controller);
+        let key_controller = gtk::EventControllerKey::new();
+        key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        key_controller.connect_key_pressed(|_, _, _, _| {
+            idle::report_activity();
+            gtk::Inhibit(false)
+        });
+        window.add_controller(key_controller);
+
+        // Sync on startup and every five minutes
+        imp.sidebar_projects.sync_projects();
+        glib::timeout_add_seconds_local(
+            300,
+            glib::clone!(@weak window => @default-return glib::Continue(false), move || {
+                window.imp().sidebar_projects.sync_projects();
+                glib::Continue(true)
+            }),
+        );
+
         if let Some(display) = gdk::Display::default() {
             let provider = gtk::CssProvider::new();
             provider.load_from_resource("/ir/imansalmani/iplan/ui/style.css");
@@ -278,6 +420,329 @@ impl IPlanWindow {
         self.property("project")
     }
 
+    // Shows tasks that are added from outside of this window
+    pub fn reload(&self) {
+        let imp = self.imp();
+        if self.showing_smart_list() {
+            imp.smart_view.reload();
+        } else {
+            self.activate_action("project.open", None)
+                .expect("Failed to send project.open action");
+        }
+        imp.sidebar_projects
+            .set_today_count(SmartView::today_tasks().len());
+    }
+
+    pub fn showing_smart_list(&self) -> bool {
+        self.imp().content_stack.visible_child_name().as_deref() == Some("smart")
+    }
+
+    fn show_smart_list(&self, list: SmartList) {
+        let imp = self.imp();
+        imp.content_stack.set_visible_child_name("smart");
+        imp.project_header.set_visible(false);
+        imp.project_layout_button.set_visible(false);
+        imp.smart_view.open(list);
+        imp.sidebar_projects.select_active_project();
+    }
+
+    fn show_project_page(&self) {
+        let imp = self.imp();
+        imp.content_stack.set_visible_child_name("project");
+        imp.project_header.set_visible(true);
+        imp.project_layout_button.set_visible(true);
+        imp.sidebar_projects.select_active_project();
+    }
+
+    // The first check of each budget only remembers its state, so alerts show on crossing
+    fn check_budgets(&self) {
+        let timer_service = TimerService::default();
+        // The task of the timer may have been deleted meanwhile
+        let running = timer_service
+            .record()
+            .and_then(|record| read_task(record.task()).ok())
+            .map(|task| (task, timer_service.elapsed()));
+        let mut budgets = vec![];
+        for project in read_projects(false).expect("Failed to read projects") {
+            let running_elapsed = match &running {
+                Some((task, elapsed)) if task.project() == project.id() => *elapsed,
+                _ => 0,
+            };
+            if project.budget() > 0 {
+                budgets.push((
+                    format!("project-{}", project.id()),
+                    project.name(),
+                    BudgetPeriod::from_index(project.budget_period()).start(),
+                    project.budget_used() + running_elapsed,
+                    project.budget(),
+                ));
+            }
+            for list in read_lists(project.id()).expect("Failed to read lists") {
+                if list.budget() == 0 {
+                    continue;
+                }
+                let running_elapsed = match &running {
+                    Some((task, elapsed)) if task.list() == list.id() => *elapsed,
+                    _ => 0,
+                };
+                budgets.push((
+                    format!("list-{}", list.id()),
+                    list.name(),
+                    BudgetPeriod::from_index(list.budget_period()).start(),
+                    list.budget_used() + running_elapsed,
+                    list.budget(),
+                ));
+            }
+        }
+
+        let mut budget_alerts = self.imp().budget_alerts.borrow_mut();
+        for (key, name, period_start, used, budget) in budgets {
+            let threshold = if used >= budget {
+                2
+            } else if used * 5 >= budget * 4 {
+                1
+            } else {
+                0
+            };
+            let previous = budget_alerts.insert(key, (period_start, threshold));
+            match previous {
+                Some((previous_start, previous_threshold))
+                    if previous_start == period_start && threshold > previous_threshold => {}
+                _ => continue,
+            }
+            let message = if threshold == 2 {
+                gettext("\"{}\" is over its time budget")
+            } else {
+                gettext("\"{}\" used 80% of its time budget")
+            }
+            .replace("{}", &name);
+            if self.is_active() {
+                self.imp()
+                    .toast_overlay
+                    .add_toast(adw::Toast::new(&message));
+            } else if let Some(app) = self.application() {
+                let notification = gio::Notification::new(&gettext("Time Budget"));
+                notification.set_body(Some(&message));
+                app.send_notification(Some("budget"), &notification);
+            }
+        }
+    }
+
+    fn ask_idle_time(&self, idle_start: i64) {
+        let timer_service = TimerService::default();
+        let record = match timer_service.record() {
+            Some(record) => record,
+            None => return,
+        };
+        let task = match read_task(record.task()) {
+            Ok(task) => task,
+            Err(_) => return,
+        };
+        let dialog = gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/idle_dialog.ui")
+            .object::<adw::MessageDialog>("dialog")
+            .unwrap();
+        dialog.set_transient_for(Some(self));
+        let idle_minutes = (glib::DateTime::now_local().unwrap().to_unix() - idle_start) / 60;
+        dialog.set_body(
+            &ngettext(
+                "You were away for {minutes} minute while the timer of \"{task}\" was running. \
+                Split keeps the time as a separate record.",
+                "You were away for {minutes} minutes while the timer of \"{task}\" was running. \
+                Split keeps the time as a separate record.",
+                idle_minutes as u32,
+            )
+            .replace("{minutes}", &idle_minutes.to_string())
+            .replace("{task}", &task.name()),
+        );
+        dialog.connect_response(None, move |_dialog, response| {
+            let timer_service = TimerService::default();
+            if timer_service.record().map(|r| r.id()) != Some(record.id()) {
+                return;
+            }
+            match response {
+                "discard" => timer_service.remove_idle_time(idle_start, false),
+                "split" => timer_service.remove_idle_time(idle_start, true),
+                _ => Ok(()),
+            }
+            .expect("Failed to remove idle time");
+        });
+        dialog.present();
+    }
+
+    // The suggested end is the last moment the computer was awake, or an hour after the start
+    fn check_stale_timer(&self, last_active: Option<i64>) {
+        let timer_service = TimerService::default();
+        let record = match timer_service.stale_record() {
+            Some(record) => record,
+            None => return,
+        };
+        let task = match read_task(record.task()) {
+            Ok(task) => task,
+            Err(_) => return,
+        };
+        DateRow::static_type();
+        TimeRow::static_type();
+        let builder = gtk::Builder::from_resource("/ir/imansalmani/iplan/ui/stale_timer_dialog.ui");
+        let dialog = builder.object::<adw::MessageDialog>("dialog").unwrap();
+        let end_date_row = builder.object::<DateRow>("end_date_row").unwrap();
+        let end_time_row = builder.object::<TimeRow>("end_time_row").unwrap();
+        dialog.set_transient_for(Some(self));
+        let start = glib::DateTime::from_unix_local(record.start()).unwrap();
+        dialog.set_body(
+            &gettext("The timer of \"{task}\" is running since {start}.")
+                .replace("{task}", &task.name())
+                .replace("{start}", &start.format("%c").unwrap()),
+        );
+        let end = last_active.unwrap_or(record.start() + 3600);
+        let end = glib::DateTime::from_unix_local(end).unwrap();
+        end_date_row.set_date(
+            end.year() as u16,
+            end.month() as u8,
+            end.day_of_month() as u8,
+        );
+        end_time_row.set_time_from_digits(end.hour(), end.minute(), end.seconds());
+        dialog.connect_response(None, move |_dialog, response| {
+            let timer_service = TimerService::default();
+            // The record may have been stopped from somewhere else meanwhile
+            if timer_service.record().map(|r| r.id()) != Some(record.id()) {
+                return;
+            }
+            match response {
+                "trim" => {
+                    let end = end_date_row
+                        .calculate_datetime()
+                        .add_seconds(end_time_row.time() as f64)
+                        .unwrap()
+                        .to_unix();
+                    timer_service.trim(end).expect("Failed to trim timer");
+                }
+                "discard" => timer_service.discard().expect("Failed to discard timer"),
+                _ => (),
+            }
+        });
+        dialog.present();
+    }
+
+    fn export_project(&self, format: ExportFormat) {
+        let project = self.project();
+        let text = match format {
+            ExportFormat::TodoTxt => todo_txt::export_project(&project),
+            ExportFormat::ICalendar => icalendar::export_project(&project),
+            ExportFormat::Outline(format) => outline::export_project(&project, format),
+        };
+        match text {
+            Ok(text) => {
+                self.save_export(text, format!("{}.{}", project.name(), format.extension()))
+            }
+            Err(err) => self.show_export_error(&err),
+        }
+    }
+
+    fn export_projects(&self, format: OutlineFormat) {
+        match outline::export_projects(format) {
+            Ok(text) => self.save_export(text, format!("IPlan.{}", format.extension())),
+            Err(err) => self.show_export_error(&err),
+        }
+    }
+
+    fn show_export_error(&self, err: &rusqlite::Error) {
+        let message = gettext("Failed to export: {}").replace("{}", &err.to_string());
+        self.imp()
+            .toast_overlay
+            .add_toast(adw::Toast::new(&message));
+    }
+
+    fn print_project(&self, content: PrintContent, export: bool) {
+        let project = self.project();
+        let markup = match content.markup(&project) {
+            Ok(markup) => markup,
+            Err(err) => {
+                let message = gettext("Failed to read project: {}").replace("{}", &err.to_string());
+                self.imp()
+                    .toast_overlay
+                    .add_toast(adw::Toast::new(&message));
+                return;
+            }
+        };
+        if !export {
+            if let Err(err) = print_markup(self, &project.name(), markup, None) {
+                let toast = adw::Toast::new(err.message());
+                self.imp().toast_overlay.add_toast(toast);
+            }
+            return;
+        }
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        dialog.set_initial_name(Some(&content.file_name(&project)));
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    let result = print_markup(&obj, &project.name(), markup, file.path());
+                    if let Err(err) = result {
+                        let toast = adw::Toast::new(err.message());
+                        obj.imp().toast_overlay.add_toast(toast);
+                    }
+                }
+            }),
+        );
+    }
+
+    fn save_export(&self, text: String, file_name: String) {
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Export")));
+        dialog.set_initial_name(Some(&file_name));
+        dialog.save(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    if let Err(err) = fs::write(file.path().unwrap(), text) {
+                        let toast = adw::Toast::new(&err.to_string());
+                        obj.imp().toast_overlay.add_toast(toast);
+                    }
+                }
+            }),
+        );
+    }
+
+    fn import_todo_txt(&self) {
+        let dialog = gtk::FileDialog::new();
+        dialog.set_accept_label(Some(&gettext("Import")));
+        dialog.open(
+            Some(self),
+            Some(&gio::Cancellable::new()),
+            glib::clone!(@weak self as obj => move |file| {
+                if let Ok(file) = file {
+                    let imp = obj.imp();
+                    let text = match fs::read_to_string(file.path().unwrap()) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            imp.toast_overlay.add_toast(adw::Toast::new(&err.to_string()));
+                            return;
+                        }
+                    };
+                    let result = todo_txt::import(&text, obj.project().id());
+                    // Tasks before a failure are already imported
+                    imp.sidebar_projects.refresh_projects();
+                    obj.activate_action("project.open", None)
+                        .expect("Failed to send project.open action");
+                    let message = match result {
+                        Ok(count) => {
+                            ngettext("{} task imported", "{} tasks imported", count as u32)
+                                .replace("{}", &count.to_string())
+                        }
+                        Err(err) => gettext("Failed to import tasks: {}")
+                            .replace("{}", &err.to_string()),
+                    };
+                    let toast = adw::Toast::new(&message);
+                    imp.toast_overlay.add_toast(toast);
+                }
+            }),
+        );
+    }
+
     #[template_callback]
     fn handle_project_layout_button_clicked(&self, button: gtk::Button) {
         let imp = self.imp();